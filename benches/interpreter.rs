@@ -0,0 +1,61 @@
+use std::time::Duration;
+
+use chip_8::{engines::HeadlessEngine, Chip8, Engine, Recompiler, Scheduler};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+const FRAMES: usize = 1_000;
+const ROMS: [&str; 3] = [
+    "roms/Particle Demo [zeroZshadow, 2008].ch8",
+    "roms/Sierpinski [Sergey Naydenov, 2010].ch8",
+    "roms/Trip8 Demo (2008) [Revival Studios].ch8",
+];
+
+fn run(data: &[u8], decode_cache: bool) {
+    let mut chip = Chip8::new();
+    chip.set_decode_cache(decode_cache);
+    chip.load_game(data);
+
+    HeadlessEngine::new(FRAMES).start_loop(&mut chip, &Scheduler::default());
+}
+
+fn run_recompiled(data: &[u8]) {
+    let mut chip = Chip8::new();
+    chip.load_game(data);
+    let mut engine = HeadlessEngine::new(0);
+    let mut recompiler = Recompiler::new();
+    let ipf = Scheduler::default().instructions_per_frame() as usize;
+
+    for _ in 0..FRAMES {
+        recompiler.run(&mut chip, &mut engine, ipf);
+        chip.decrement_timers();
+    }
+}
+
+fn decode_cache(c: &mut Criterion) {
+    let mut group = c.benchmark_group("decode_cache");
+    group
+        .measurement_time(Duration::from_secs(10))
+        .throughput(Throughput::Elements(
+            FRAMES as u64 * Scheduler::default().instructions_per_frame() as u64,
+        ));
+
+    for path in ROMS {
+        let data = std::fs::read(path).unwrap();
+        let name = path.trim_start_matches("roms/");
+
+        group.bench_with_input(BenchmarkId::new("uncached", name), &data, |b, data| {
+            b.iter(|| run(data, false))
+        });
+        group.bench_with_input(BenchmarkId::new("cached", name), &data, |b, data| {
+            b.iter(|| run(data, true))
+        });
+        group.bench_with_input(BenchmarkId::new("recompiled", name), &data, |b, data| {
+            b.iter(|| run_recompiled(data))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, decode_cache);
+criterion_main!(benches);
@@ -0,0 +1,162 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use comfy_table::Table;
+
+use crate::{
+    engines::HeadlessEngine, Chip8, FrameState, Quircks, Recompiler, Scheduler, Seed, Timing,
+};
+
+pub struct BenchmarkReport {
+    cycles: u64,
+    frames: u64,
+    elapsed: Duration,
+    /// (opcode pattern, executed count, total time spent)
+    classes: Vec<(&'static str, u64, Duration)>,
+}
+
+impl BenchmarkReport {
+    pub fn instructions_per_second(&self) -> f64 {
+        Self::rate(self.cycles as f64, self.elapsed.as_secs_f64())
+    }
+
+    pub fn frames_per_second(&self) -> f64 {
+        Self::rate(self.frames as f64, self.elapsed.as_secs_f64())
+    }
+
+    /// Average instructions per frame
+    pub fn instructions_per_frame(&self) -> f64 {
+        Self::rate(self.cycles as f64, self.frames as f64)
+    }
+
+    /// Zero instead of NaN or infinity when nothing ran
+    fn rate(count: f64, per: f64) -> f64 {
+        if per > 0.0 {
+            count / per
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Runs `rom` headless with `quircks` and `seed` for at least `cycles`
+/// instructions as fast as possible.
+///
+/// The ROM is run twice: once frame by frame like `scheduler` runs it to
+/// measure throughput and once with every instruction timed separately to
+/// break the time down by opcode. Timing each instruction has a lot of
+/// overhead, so the breakdown is only useful to compare opcodes with each
+/// other, and its timers tick every `Scheduler::instructions_per_frame`.
+///
+/// With `recompile` the throughput run goes through a [`Recompiler`] instead
+/// of the interpreter. It has no cycle costs, so VIP timing is not supported.
+pub fn run_benchmark(
+    rom: &[u8],
+    cycles: u64,
+    scheduler: &Scheduler,
+    quircks: Quircks,
+    seed: Seed,
+    recompile: bool,
+) -> BenchmarkReport {
+    assert!(
+        !recompile || scheduler.timing == Timing::Fixed,
+        "the recompiler only supports fixed timing"
+    );
+
+    // both runs draw the same numbers
+    let seed = Seed::Fixed(seed.value());
+    let load = || {
+        let mut chip = Chip8::new();
+        chip.quircks = quircks;
+        chip.set_rng(seed.into_rng());
+        chip.load_game(rom);
+        chip
+    };
+
+    let mut chip = load();
+    let mut engine = HeadlessEngine::new(0);
+    let mut recompiler = Recompiler::new();
+    let mut state = FrameState::default();
+    let mut frames = 0;
+
+    let start = Instant::now();
+    while chip.cycles() < cycles {
+        if recompile {
+            let instructions = scheduler.frame_instructions(&mut state);
+            recompiler.run(&mut chip, &mut engine, instructions as usize);
+            chip.decrement_timers();
+        } else {
+            scheduler.run_frame(&mut state, &mut chip, &mut engine);
+        }
+        frames += 1;
+    }
+    let elapsed = start.elapsed();
+    let executed = chip.cycles();
+
+    let instructions_per_frame = scheduler.instructions_per_frame() as u64;
+    let mut chip = load();
+    let mut engine = HeadlessEngine::new(0);
+
+    let mut classes: HashMap<&'static str, (u64, Duration)> = HashMap::new();
+    for cycle in 0..cycles {
+        let pattern = chip
+            .current_instruction()
+            .map_or("????", |instruction| instruction.pattern());
+
+        let start = Instant::now();
+        chip.emulate_cycle(&mut engine);
+        let spent = start.elapsed();
+
+        let class = classes.entry(pattern).or_default();
+        class.0 += 1;
+        class.1 += spent;
+
+        if cycle % instructions_per_frame == instructions_per_frame - 1 {
+            chip.decrement_timers();
+        }
+    }
+
+    let mut classes: Vec<_> = classes
+        .into_iter()
+        .map(|(pattern, (count, spent))| (pattern, count, spent))
+        .collect();
+    classes.sort_by_key(|class| std::cmp::Reverse(class.2));
+
+    BenchmarkReport {
+        cycles: executed,
+        frames,
+        elapsed,
+        classes,
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} instructions in {:.3?}", self.cycles, self.elapsed)?;
+        writeln!(
+            f,
+            "{:.0} instructions/s, {:.0} frames/s ({:.1} instructions per frame)",
+            self.instructions_per_second(),
+            self.frames_per_second(),
+            self.instructions_per_frame()
+        )?;
+
+        let total: Duration = self.classes.iter().map(|class| class.2).sum();
+        let mut table = Table::new();
+        table.set_header(vec!["Opcode", "Count", "Total time", "ns/op", "Share"]);
+        for (pattern, count, spent) in &self.classes {
+            table.add_row(vec![
+                pattern.to_string(),
+                count.to_string(),
+                format!("{:.3?}", spent),
+                format!("{:.1}", spent.as_nanos() as f64 / *count as f64),
+                format!("{:.1}%", 100.0 * spent.as_secs_f64() / total.as_secs_f64()),
+            ]);
+        }
+
+        write!(f, "{}", table)
+    }
+}
@@ -1,15 +1,15 @@
-use std::fmt;
+use std::{collections::VecDeque, fmt, ops::RangeInclusive, str::FromStr};
 
 use comfy_table::Table;
 
-use crate::{word_to_nibbles, Engine, MergeNibbles, FONTSET};
+use crate::{Engine, Instruction, Profile, Rng, XorShiftRng, FONTSET};
 
 #[derive(Debug, Default)]
-struct Registers {
-    v: [u8; 16],
-    i: u16,
-    sp: u8,
-    pc: u16,
+pub(crate) struct Registers {
+    pub(crate) v: [u8; 16],
+    pub(crate) i: u16,
+    pub(crate) sp: u8,
+    pub(crate) pc: u16,
 }
 
 impl Registers {
@@ -21,21 +21,108 @@ impl Registers {
     }
 }
 
-#[derive(Default)]
+/// Behaviors that differ between CHIP-8 interpreters
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub struct Quircks {
+    /// FX55 and FX65 increment I
     pub load_store: bool,
+    /// 8XY6 and 8XYE shift VX in place instead of VY into VX
+    pub shift: bool,
+    /// BNNN jumps to XNN plus VX instead of NNN plus V0
+    pub jump: bool,
+    /// 8XY1, 8XY2 and 8XY3 set VF to 0
+    pub vf_reset: bool,
+}
+
+impl Quircks {
+    /// Names used by `Display` and `FromStr`
+    fn flags(&mut self) -> [(&'static str, &mut bool); 4] {
+        [
+            ("load_store", &mut self.load_store),
+            ("shift", &mut self.shift),
+            ("jump", &mut self.jump),
+            ("vf_reset", &mut self.vf_reset),
+        ]
+    }
+}
+
+/// Enabled quirks separated by commas, or `none`
+impl fmt::Display for Quircks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut quircks = *self;
+        let enabled: Vec<_> = quircks
+            .flags()
+            .into_iter()
+            .filter_map(|(name, flag)| flag.then_some(name))
+            .collect();
+
+        if enabled.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", enabled.join(","))
+        }
+    }
+}
+
+impl FromStr for Quircks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut quircks = Quircks::default();
+        for name in s.split(',').filter(|&name| name != "none") {
+            let mut flags = quircks.flags();
+            let Some((_, flag)) = flags.iter_mut().find(|(flag, _)| *flag == name) else {
+                let names: Vec<_> = flags.iter().map(|(name, _)| *name).collect();
+                return Err(format!(
+                    "unknown quirk {}, expected {}",
+                    name,
+                    names.join(", ")
+                ));
+            };
+            **flag = true;
+        }
+
+        Ok(quircks)
+    }
+}
+
+/// Copy of the registers, stack and timers, mainly for comparing two emulators
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuState {
+    pub v: [u8; 16],
+    pub i: u16,
+    pub pc: u16,
+    pub sp: u8,
+    pub stack: [u16; 16],
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    /// See [`Rng::state`]
+    pub rng_state: Option<u64>,
 }
 
 pub struct Chip8 {
-    mem: [u8; 4096],
-    regs: Registers,
+    pub(crate) mem: [u8; 4096],
+    pub(crate) regs: Registers,
     stack: [u16; 16],
     key_state: [bool; 16],
     delay_timer: u8,
     sound_timer: u8,
     pub quircks: Quircks,
+    rng: Box<dyn Rng>,
+    /// Decoded instructions keyed by address, `None` in a slot means "not decoded yet"
+    decode_cache: Option<Box<[Option<Instruction>; 4096]>>,
+    /// Addresses written since the last `take_written` call
+    written: Option<(usize, usize)>,
+    /// Instructions run by `emulate_cycle` and `Recompiler::run`
+    cycles: u64,
+    /// Address and height of the last DXYN sprites, newest first
+    recent_sprites: VecDeque<(u16, u8)>,
+    profile: Option<Profile>,
 }
 
+/// How many DXYN sources `Chip8::recent_sprites` remembers
+pub const RECENT_SPRITES: usize = 8;
+
 impl Chip8 {
     pub fn new() -> Chip8 {
         let mut mem = [0; 4096];
@@ -49,232 +136,391 @@ impl Chip8 {
             sound_timer: 0,
             key_state: [false; 16],
             quircks: Quircks::default(),
+            rng: Box::new(XorShiftRng::default()),
+            decode_cache: Some(Box::new([None; 4096])),
+            written: None,
+            cycles: 0,
+            recent_sprites: VecDeque::with_capacity(RECENT_SPRITES + 1),
+            profile: None,
         }
     }
 
     pub fn load_game(&mut self, data: &[u8]) {
         self.mem[0x200..0x200 + data.len()].copy_from_slice(data);
+        self.set_decode_cache(self.decode_cache.is_some());
+        self.mark_written(0x200, 0x200 + data.len());
+    }
+
+    pub fn cpu_state(&self) -> CpuState {
+        CpuState {
+            v: self.regs.v,
+            i: self.regs.i,
+            pc: self.regs.pc,
+            sp: self.regs.sp,
+            stack: self.stack,
+            delay_timer: self.delay_timer,
+            sound_timer: self.sound_timer,
+            rng_state: self.rng.state(),
+        }
+    }
+
+    /// Overwrites the registers, stack and timers, and the state of the
+    /// random number generator unless `rng_state` is `None`
+    pub fn set_cpu_state(&mut self, state: &CpuState) {
+        self.regs.v = state.v;
+        self.regs.i = state.i;
+        self.regs.pc = state.pc;
+        self.regs.sp = state.sp;
+        self.stack = state.stack;
+        self.delay_timer = state.delay_timer;
+        self.sound_timer = state.sound_timer;
+        if let Some(rng_state) = state.rng_state {
+            self.rng.set_state(rng_state);
+        }
+    }
+
+    /// Pressed keys of the hex keypad, bit N is key N
+    pub fn keypad(&self) -> u16 {
+        self.key_state
+            .iter()
+            .enumerate()
+            .fold(0, |mask, (key, &down)| mask | (down as u16) << key)
+    }
+
+    pub fn set_keypad(&mut self, mask: u16) {
+        for (key, down) in self.key_state.iter_mut().enumerate() {
+            *down = mask & (1 << key) != 0;
+        }
+    }
+
+    pub fn set_key(&mut self, key: u8, pressed: bool) {
+        self.key_state[key as usize & 0xF] = pressed;
+    }
+
+    /// Replaces the random number generator used by CXNN
+    pub fn set_rng(&mut self, rng: Box<dyn Rng>) {
+        self.rng = rng;
+    }
+
+    /// Number of instructions run so far, by the interpreter or the recompiler
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Address and height of the sprites drawn last, newest first
+    pub fn recent_sprites(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.recent_sprites.iter().copied()
+    }
+
+    /// Enables or disables counting the instructions run by `emulate_cycle`
+    /// (disabled by default). The counts are reset in both cases.
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profile = enabled.then(Profile::new);
+    }
+
+    /// Counts collected since profiling was enabled
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.regs.pc
+    }
+
+    pub fn memory(&self) -> &[u8; 4096] {
+        &self.mem
+    }
+
+    /// Writes `data` from `address` on, wrapping around at the end of memory
+    pub fn write_memory(&mut self, address: u16, data: &[u8]) {
+        for (offset, &byte) in data.iter().enumerate() {
+            self.write_mem(address as usize + offset, byte);
+        }
+    }
+
+    /// Decodes the instruction at PC without executing it
+    pub fn current_instruction(&self) -> Option<Instruction> {
+        Instruction::decode(self.opcode_at(self.regs.pc as usize))
+    }
+
+    /// The two bytes at `address`, the second one wraps around to 000
+    fn opcode_at(&self, address: usize) -> u16 {
+        u16::from_be_bytes([self.mem[address & 0xFFF], self.mem[(address + 1) & 0xFFF]])
+    }
+
+    /// Enables or disables caching of decoded instructions (enabled by default).
+    /// The cache is dropped in both cases.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = enabled.then(|| Box::new([None; 4096]));
+    }
+
+    fn mark_written(&mut self, start: usize, end: usize) {
+        self.written = Some(match self.written {
+            Some((lo, hi)) => (lo.min(start), hi.max(end)),
+            None => (start, end),
+        });
+    }
+
+    /// Returns the range of memory modified since the previous call
+    pub(crate) fn take_written(&mut self) -> Option<RangeInclusive<usize>> {
+        self.written.take().map(|(start, end)| start..=end)
+    }
+
+    /// Addresses wrap around at the end of memory
+    fn write_mem(&mut self, address: usize, value: u8) {
+        let address = address & 0xFFF;
+        self.mem[address] = value;
+        self.mark_written(address, address);
+
+        if let Some(cache) = &mut self.decode_cache {
+            // an instruction is two bytes, so it may start at the previous address too
+            cache[address] = None;
+            cache[address.wrapping_sub(1) & 0xFFF] = None;
+        }
+    }
+
+    fn fetch(&mut self) -> Instruction {
+        let pc = self.regs.pc as usize & 0xFFF;
+        if let Some(instruction) = self.decode_cache.as_ref().and_then(|cache| cache[pc]) {
+            return instruction;
+        }
+
+        let opcode = self.opcode_at(pc);
+        let instruction = match Instruction::decode(opcode) {
+            Some(instruction) => instruction,
+            None => panic!("unknown instruction {:#06X}", opcode),
+        };
+        if let Some(cache) = &mut self.decode_cache {
+            cache[pc] = Some(instruction);
+        }
+
+        instruction
     }
 
     fn skip_next_instruction(&mut self) {
-        self.regs.pc += 2;
+        self.regs.pc = self.regs.pc.wrapping_add(2);
     }
 
     fn jump(&mut self, address: u16) {
-        // -2 because we unconditionally subtract every cycle
-        self.regs.pc = address - 2;
+        // -2 because we unconditionally add 2 every cycle, wraps for jumps to 000
+        self.regs.pc = address.wrapping_sub(2);
     }
 
+    // the stack wraps around after 16 entries
     fn stack_push(&mut self, value: u16) {
-        self.stack[self.regs.sp as usize] = value;
-        self.regs.sp += 1;
+        self.stack[self.regs.sp as usize & 0xF] = value;
+        self.regs.sp = (self.regs.sp + 1) & 0xF;
     }
 
     fn stack_pop(&mut self) -> u16 {
-        self.regs.sp -= 1;
+        self.regs.sp = self.regs.sp.wrapping_sub(1) & 0xF;
         self.stack[self.regs.sp as usize]
     }
 
     fn call(&mut self, address: u16) {
-        self.stack_push(self.regs.pc + 2);
+        self.stack_push(self.regs.pc.wrapping_add(2) & 0xFFF);
         self.jump(address);
     }
 
     pub fn emulate_cycle<T: Engine>(&mut self, engine: &mut T) {
+        let instruction = self.fetch();
+        self.count(instruction);
+        self.execute(instruction, engine);
+    }
+
+    /// Counts `instruction` at PC as run, before running it
+    pub(crate) fn count(&mut self, instruction: Instruction) {
+        self.cycles += 1;
+        if let Some(profile) = &mut self.profile {
+            profile.record(self.regs.pc, instruction);
+        }
+    }
+
+    /// Clears VF after a logic instruction with the `vf_reset` quirk
+    pub(crate) fn reset_vf(&mut self) {
+        if self.quircks.vf_reset {
+            self.regs.v[0xF] = 0;
+        }
+    }
+
+    pub(crate) fn execute<T: Engine>(&mut self, instruction: Instruction, engine: &mut T) {
+        use Instruction::*;
+
         macro_rules! v {
             ($name:tt) => {
                 self.regs.v[($name) as usize]
             };
         }
 
-        let instruction: [u8; 2] = [
-            self.mem[self.regs.pc as usize],
-            self.mem[self.regs.pc as usize + 1],
-        ];
-        match word_to_nibbles(&instruction) {
-            // 00E0
-            [0, 0, 0xE, 0] => {
+        match instruction {
+            Cls => {
                 engine.clear_screen();
             }
-            // 00EE
-            [0, 0, 0xE, 0xE] => {
+            Ret => {
                 let address = self.stack_pop();
                 self.jump(address);
             }
-            // 0NNN Call
-            [0, _nnn @ ..] => {
-                todo!();
-                // self.call(nnn.merge_nibbles());
-
-                // let mem_loc = nnn.merge_nibbles() as usize;
-                // let address = ((self.mem[mem_loc] as u16) << 8) | (self.mem[mem_loc + 1] as u16);
-                // self.stack_push(address);
-                // self.jump(nnn.merge_nibbles());
-            }
-            // 1NNN
-            [1, nnn @ ..] => {
-                self.jump(nnn.merge_nibbles());
+            // machine code routines can't run here, like most interpreters skip them
+            Sys { nnn: _ } => {}
+            Jump { nnn } => {
+                self.jump(nnn);
             }
-            // 2NNN
-            [0x2, nnn @ ..] => {
-                self.call(nnn.merge_nibbles());
+            Call { nnn } => {
+                self.call(nnn);
             }
-            // 3XNN
-            [0x3, x, nn @ ..] => {
-                if v!(x) == nn.merge_nibbles() {
+            SkipEqImm { x, nn } => {
+                if v!(x) == nn {
                     self.skip_next_instruction();
                 }
             }
-            // 4XNN
-            [0x4, x, nn @ ..] => {
-                if v!(x) != nn.merge_nibbles() {
+            SkipNeImm { x, nn } => {
+                if v!(x) != nn {
                     self.skip_next_instruction();
                 }
             }
-            // 5XY0
-            [0x5, x, y, 0] => {
+            SkipEqReg { x, y } => {
                 if v!(x) == v!(y) {
                     self.skip_next_instruction();
                 }
             }
-            // 6XNN
-            [0x6, x, nn @ ..] => {
-                v![x] = nn.merge_nibbles();
+            LoadImm { x, nn } => {
+                v![x] = nn;
             }
-            // 7XNN
-            [0x7, x, nn @ ..] => {
-                v![x] = v![x].wrapping_add(nn.merge_nibbles());
+            AddImm { x, nn } => {
+                v![x] = v![x].wrapping_add(nn);
             }
-            // 8XY0
-            [0x8, x, y, 0] => {
+            Load { x, y } => {
                 v![x] = v![y];
             }
-            // 8XY1
-            [0x8, x, y, 1] => v![x] |= v![y],
-            // 8XY2
-            [0x8, x, y, 0x2] => v![x] &= v![y],
-            // 8XY3
-            [0x8, x, y, 0x3] => v![x] ^= v![y],
-            // 8XY4
-            [0x8, x, y, 0x4] => {
+            Or { x, y } => {
+                v![x] |= v![y];
+                self.reset_vf();
+            }
+            And { x, y } => {
+                v![x] &= v![y];
+                self.reset_vf();
+            }
+            Xor { x, y } => {
+                v![x] ^= v![y];
+                self.reset_vf();
+            }
+            Add { x, y } => {
                 let sum = v![x] as u16 + v![y] as u16;
                 v![x] = sum as u8;
                 v![0xF] = (sum > 0xFF) as u8;
             }
-            // 8XY5
-            [0x8, x, y, 0x5] => {
+            // VF is written after the result, so it holds the flag when X is F
+            Sub { x, y } => {
                 let borrow = v![x] < v![y];
-                v![0xF] = !borrow as u8;
                 v![x] = v![x].wrapping_sub(v![y]);
+                v![0xF] = !borrow as u8;
             }
-            // 8XY6
-            [0x8, x, y, 0x6] => {
-                v![0xF] = v![y] & 0b0000_0001;
-                v![x] = v![y] >> 1;
+            Shr { x, y } => {
+                let source = if self.quircks.shift { v![x] } else { v![y] };
+                v![x] = source >> 1;
+                v![0xF] = source & 0b0000_0001;
             }
-            // 8XY7
-            [0x8, x, y, 0x7] => {
+            SubN { x, y } => {
                 let borrow = v![x] > v![y];
+                v![x] = v![y].wrapping_sub(v![x]);
                 v![0xF] = !borrow as u8;
-                v![x] = v![y] - v![x];
             }
-            // 8XYE
-            [0x8, x, y, 0xE] => {
-                v![0xF] = (v![y] & 0b1000_0000) >> 7;
-                v![x] = v![y] << 1;
+            Shl { x, y } => {
+                let source = if self.quircks.shift { v![x] } else { v![y] };
+                v![x] = source << 1;
+                v![0xF] = (source & 0b1000_0000) >> 7;
             }
-            // 9XY0
-            [0x9, x, y, 0] => {
+            SkipNeReg { x, y } => {
                 if v![x] != v![y] {
                     self.skip_next_instruction();
                 }
             }
-            // ANNN
-            [0xA, nnn @ ..] => {
-                self.regs.i = nnn.merge_nibbles();
+            LoadI { nnn } => {
+                self.regs.i = nnn;
             }
-            // BNNN
-            [0xB, nnn @ ..] => {
-                self.jump(v![0] as u16 + nnn.merge_nibbles());
+            JumpV0 { nnn } => {
+                let offset = if self.quircks.jump {
+                    v![(nnn >> 8)]
+                } else {
+                    v![0]
+                };
+                self.jump(offset as u16 + nnn);
             }
-            // CXNN
-            [0xC, x, nn @ ..] => {
-                v![x] = engine.rand() & nn.merge_nibbles();
+            Rand { x, nn } => {
+                v![x] = self.rng.next_u8() & nn;
             }
-            // DXYN
-            [0xD, x, y, n] => {
-                let i = self.regs.i as usize;
-                let flipped =
-                    engine.draw_sprite(v![x], v![y], n, &self.mem[i..i + (n as usize) * 8]);
+            Draw { x, y, n } => {
+                let i = self.regs.i as usize & 0xFFF;
+                if self.recent_sprites.front() != Some(&(i as u16, n)) {
+                    self.recent_sprites.push_front((i as u16, n));
+                    self.recent_sprites.truncate(RECENT_SPRITES);
+                }
+                let mut sprite = [0; 15];
+                for (row, byte) in sprite.iter_mut().enumerate().take(n as usize) {
+                    *byte = self.mem[(i + row) & 0xFFF];
+                }
+                let flipped = engine.draw_sprite(v![x], v![y], n, &sprite[..n as usize]);
                 v![0xF] = flipped as u8;
             }
-            // EX9E
-            [0xE, x, 0x9, 0xE] => {
-                if self.key_state[v![x] as usize] {
+            // only the low nibble selects a key
+            SkipKey { x } => {
+                if self.key_state[v![x] as usize & 0xF] {
                     self.skip_next_instruction();
                 }
             }
-            // EXA1
-            [0xE, x, 0xA, 0x1] => {
-                if !self.key_state[v![x] as usize] {
+            SkipNotKey { x } => {
+                if !self.key_state[v![x] as usize & 0xF] {
                     self.skip_next_instruction();
                 }
             }
-            // FX07
-            [0xF, x, 0, 0x7] => {
+            LoadDelay { x } => {
                 v![x] = self.delay_timer;
             }
-            // FX0A
-            [0xF, _x, 0, 0xA] => todo!(),
-            // FX15
-            [0xF, x, 1, 0x5] => self.delay_timer = v![x],
-            // FX18
-            [0xF, x, 1, 0x8] => self.sound_timer = v![x],
-            // FX1E
-            [0xF, x, 1, 0xE] => {
-                self.regs.i += v![x] as u16;
+            WaitKey { x } => match self.key_state.iter().position(|&down| down) {
+                Some(key) => v![x] = key as u8,
+                // run this instruction again until a key is pressed
+                None => self.regs.pc = self.regs.pc.wrapping_sub(2),
+            },
+            SetDelay { x } => self.delay_timer = v![x],
+            SetSound { x } => self.sound_timer = v![x],
+            AddI { x } => {
+                self.regs.i = self.regs.i.wrapping_add(v![x] as u16) & 0xFFF;
             }
-            // FX29
-            [0xF, x, 0x2, 0x9] => self.regs.i = v![x] as u16 * 5,
-            // FX33
-            [0xF, x, 0x3, 0x3] => {
+            LoadFont { x } => self.regs.i = (v![x] & 0xF) as u16 * 5,
+            Bcd { x } => {
                 let i = self.regs.i as usize;
-                self.mem[i] = v![x] / 100;
-                self.mem[i + 1] = (v![x] % 100) / 10;
-                self.mem[i + 2] = v![x] % 10;
+                let vx = v![x];
+                self.write_mem(i, vx / 100);
+                self.write_mem(i + 1, (vx % 100) / 10);
+                self.write_mem(i + 2, vx % 10);
             }
-            // FX55
-            [0xF, x, 0x5, 0x5] => {
+            StoreRegs { x } => {
                 // Store the values of registers V0 to VX inclusive in memory starting at address I
                 // I is set to I + X + 1 after operation
                 let offset = self.regs.i as usize;
-                for (i, v) in self.regs.v[..=x as usize].iter().enumerate() {
-                    self.mem[offset + i] = *v;
+                for i in 0..=x as usize {
+                    self.write_mem(offset + i, self.regs.v[i]);
                 }
 
                 if self.quircks.load_store {
-                    self.regs.i += x as u16 + 1;
+                    self.regs.i = self.regs.i.wrapping_add(x as u16 + 1) & 0xFFF;
                 }
             }
-            // FX65
-            [0xF, x, 0x6, 0x5] => {
+            LoadRegs { x } => {
                 // Fill registers V0 to VX inclusive with the values stored in memory starting at address I
                 // I is set to I + X + 1 after operation
                 let offset = self.regs.i as usize;
                 for (i, v) in self.regs.v[..=x as usize].iter_mut().enumerate() {
-                    *v = self.mem[offset + i];
+                    *v = self.mem[(offset + i) & 0xFFF];
                 }
 
                 if self.quircks.load_store {
-                    self.regs.i += x as u16 + 1;
+                    self.regs.i = self.regs.i.wrapping_add(x as u16 + 1) & 0xFFF;
                 }
             }
-            _ => {
-                panic!("unknown instruction {:#02X?}", instruction);
-            }
         }
 
-        self.regs.pc += 2;
+        self.regs.pc = self.regs.pc.wrapping_add(2) & 0xFFF;
     }
 
     pub fn decrement_timers(&mut self) {
@@ -0,0 +1,99 @@
+use std::fmt;
+
+/// Speed multipliers selectable with [`EmulationControl::faster`] and [`EmulationControl::slower`]
+pub const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+const NORMAL_SPEED: usize = 2;
+
+/// Pause, frame advance and speed state shared by the engines.
+///
+/// Engines map their own keys to these methods and ask
+/// [`EmulationControl::frames_to_run`] how many frames to emulate
+/// whenever the [`crate::FramePacer`] says frames are due.
+#[derive(Debug, Clone)]
+pub struct EmulationControl {
+    paused: bool,
+    speed: usize,
+    turbo: bool,
+    pending_steps: u32,
+    /// Fractional frames carried over at speeds below 1x
+    budget: f32,
+}
+
+impl Default for EmulationControl {
+    fn default() -> Self {
+        EmulationControl {
+            paused: false,
+            speed: NORMAL_SPEED,
+            turbo: false,
+            pending_steps: 0,
+            budget: 0.0,
+        }
+    }
+}
+
+impl EmulationControl {
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.pending_steps = 0;
+    }
+
+    /// Pauses and runs a single frame
+    pub fn frame_advance(&mut self) {
+        self.paused = true;
+        self.pending_steps += 1;
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed + 1).min(SPEEDS.len() - 1);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.saturating_sub(1);
+    }
+
+    pub fn reset_speed(&mut self) {
+        self.speed = NORMAL_SPEED;
+    }
+
+    /// Turbo runs at the highest speed for as long as it is set
+    pub fn set_turbo(&mut self, turbo: bool) {
+        self.turbo = turbo;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn speed(&self) -> f32 {
+        if self.turbo {
+            SPEEDS[SPEEDS.len() - 1]
+        } else {
+            SPEEDS[self.speed]
+        }
+    }
+
+    /// Turns `due` real-time frames into the number of frames to emulate
+    pub fn frames_to_run(&mut self, due: u32) -> u32 {
+        if self.paused {
+            return std::mem::take(&mut self.pending_steps);
+        }
+
+        self.budget += due as f32 * self.speed();
+        let frames = self.budget.floor();
+        self.budget -= frames;
+
+        frames as u32
+    }
+}
+
+impl fmt::Display for EmulationControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.paused {
+            write!(f, "paused")
+        } else if self.turbo {
+            write!(f, "turbo {}x", self.speed())
+        } else {
+            write!(f, "{}x", self.speed())
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use std::{collections::BTreeSet, fmt, str::FromStr};
+
+use crate::{disassemble_at, Chip8, Engine, FrameState, Scheduler};
+
+/// Frames a key stays pressed when `key` is given no duration
+const DEFAULT_KEY_FRAMES: u32 = 6;
+
+/// Commands typed into the debugger, most have a one letter alias:
+///
+/// | Command               | Action                                   |
+/// |-----------------------|------------------------------------------|
+/// | `s`, `step [n]`       | run `n` instructions (1) and pause       |
+/// | `f`, `frame [n]`      | run to the end of `n` frames (1)         |
+/// | `c`, `continue`       | run until a breakpoint                   |
+/// | `p`, `pause`          | pause                                    |
+/// | `b`, `break <addr>`   | add a breakpoint                         |
+/// | `d`, `delete [addr]`  | remove one or all breakpoints            |
+/// | `m`, `mem <addr>`     | show memory from `addr`, `pc` or `i`     |
+/// | `k`, `key <k> [n]`    | hold keypad key `k` for `n` frames (6)   |
+/// | `q`, `quit`           | exit                                     |
+///
+/// Addresses are hexadecimal with an optional `0x` or `$`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Step(u32),
+    Frame(u32),
+    Continue,
+    Pause,
+    Break(u16),
+    Delete(Option<u16>),
+    Memory(Location),
+    Key(u8, u32),
+    Quit,
+}
+
+/// Where the memory view starts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    Address(u16),
+    Pc,
+    I,
+}
+
+/// Hexadecimal address with an optional `0x` or `$`, below 0x1000
+pub fn parse_address(s: &str) -> Result<u16, String> {
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix('$'))
+        .unwrap_or(s);
+    match u16::from_str_radix(hex, 16) {
+        Ok(address) if address < 0x1000 => Ok(address),
+        _ => Err(format!("invalid address {:?}", s)),
+    }
+}
+
+fn parse_count(s: Option<&str>, default: u32) -> Result<u32, String> {
+    s.map_or(Ok(default), |s| {
+        s.parse().map_err(|_| format!("invalid count {:?}", s))
+    })
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let arg = words.next();
+        let arg2 = words.next();
+        let required = arg.ok_or_else(|| format!("{} needs an argument", command));
+
+        match command {
+            "s" | "step" => Ok(Command::Step(parse_count(arg, 1)?)),
+            "f" | "frame" => Ok(Command::Frame(parse_count(arg, 1)?)),
+            "c" | "continue" => Ok(Command::Continue),
+            "p" | "pause" => Ok(Command::Pause),
+            "b" | "break" => Ok(Command::Break(parse_address(required?)?)),
+            "d" | "delete" => Ok(Command::Delete(arg.map(parse_address).transpose()?)),
+            "m" | "mem" => Ok(Command::Memory(match required? {
+                "pc" => Location::Pc,
+                "i" => Location::I,
+                address => Location::Address(parse_address(address)?),
+            })),
+            "k" | "key" => match u8::from_str_radix(required?, 16) {
+                Ok(key) if key < 16 => {
+                    Ok(Command::Key(key, parse_count(arg2, DEFAULT_KEY_FRAMES)?))
+                }
+                _ => Err(format!("invalid key {:?}, expected 0-F", arg.unwrap())),
+            },
+            "q" | "quit" => Ok(Command::Quit),
+            _ => Err(format!("unknown command {:?}", command)),
+        }
+    }
+}
+
+/// Why the debugger paused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    Breakpoint(u16),
+    Step,
+    Frame,
+    Pause,
+}
+
+impl fmt::Display for Stop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stop::Breakpoint(address) => write!(f, "breakpoint at {:03X}", address),
+            Stop::Step => write!(f, "stepped"),
+            Stop::Frame => write!(f, "end of frame"),
+            Stop::Pause => write!(f, "paused"),
+        }
+    }
+}
+
+/// Breakpoints and stepping on top of the scheduler, independent of any UI.
+///
+/// Frames are counted in instructions, as many as the fixed timing of
+/// `Scheduler::run_frame` runs tick the timers once, so VIP timing is
+/// not modelled here.
+#[derive(Debug)]
+pub struct Debugger {
+    breakpoints: BTreeSet<u16>,
+    stopped: Option<Stop>,
+    /// Instructions run in the current frame
+    frame_cycles: u32,
+    /// Instructions in the current frame, decided when it starts
+    frame_length: Option<u32>,
+    frame_state: FrameState,
+    /// Don't stop at a breakpoint on the first instruction after resuming
+    resumed: bool,
+    /// Frames left for every held key
+    held_keys: [u32; 16],
+    memory_view: Location,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Debugger {
+            breakpoints: BTreeSet::new(),
+            stopped: Some(Stop::Pause),
+            frame_cycles: 0,
+            frame_length: None,
+            frame_state: FrameState::default(),
+            resumed: false,
+            held_keys: [0; 16],
+            memory_view: Location::Pc,
+        }
+    }
+}
+
+impl Debugger {
+    /// Starts paused before the first instruction
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.stopped.is_some()
+    }
+
+    /// Why the debugger paused, `None` while running
+    pub fn stopped(&self) -> Option<Stop> {
+        self.stopped
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<u16> {
+        &self.breakpoints
+    }
+
+    /// First address of the memory view
+    pub fn memory_view(&self, emulator: &Chip8) -> u16 {
+        match self.memory_view {
+            Location::Address(address) => address,
+            Location::Pc => emulator.pc(),
+            Location::I => emulator.cpu_state().i,
+        }
+    }
+
+    /// Runs `command`, returns `false` for `quit`
+    pub fn execute<T: Engine>(
+        &mut self,
+        command: Command,
+        emulator: &mut Chip8,
+        engine: &mut T,
+        scheduler: &Scheduler,
+    ) -> bool {
+        match command {
+            Command::Step(steps) => {
+                for _ in 0..steps {
+                    self.cycle(emulator, engine, scheduler);
+                }
+                self.stopped = Some(Stop::Step);
+            }
+            Command::Frame(frames) => {
+                self.resumed = true;
+                for _ in 0..frames {
+                    self.stopped = None;
+                    self.run_frame(emulator, engine, scheduler);
+                    if self.stopped.is_some() {
+                        return true;
+                    }
+                }
+                self.stopped = Some(Stop::Frame);
+            }
+            Command::Continue => {
+                self.stopped = None;
+                self.resumed = true;
+            }
+            Command::Pause => self.stopped = Some(Stop::Pause),
+            Command::Break(address) => {
+                self.breakpoints.insert(address);
+            }
+            Command::Delete(Some(address)) => {
+                self.breakpoints.remove(&address);
+            }
+            Command::Delete(None) => self.breakpoints.clear(),
+            Command::Memory(location) => self.memory_view = location,
+            Command::Key(key, frames) => {
+                self.held_keys[key as usize] = frames;
+                emulator.set_key(key, frames > 0);
+            }
+            Command::Quit => return false,
+        }
+
+        true
+    }
+
+    /// Runs the rest of the current frame unless paused,
+    /// stopping early at breakpoints
+    pub fn run_frame<T: Engine>(
+        &mut self,
+        emulator: &mut Chip8,
+        engine: &mut T,
+        scheduler: &Scheduler,
+    ) {
+        if self.stopped.is_some() {
+            return;
+        }
+
+        loop {
+            if self.skip_empty_frame(emulator, scheduler) {
+                return;
+            }
+
+            let pc = emulator.pc();
+            if !self.resumed && self.breakpoints.contains(&pc) {
+                self.stopped = Some(Stop::Breakpoint(pc));
+                return;
+            }
+            self.resumed = false;
+
+            if self.cycle(emulator, engine, scheduler) {
+                return;
+            }
+        }
+    }
+
+    /// Runs one instruction, returns `true` when it completed a frame
+    fn cycle<T: Engine>(
+        &mut self,
+        emulator: &mut Chip8,
+        engine: &mut T,
+        scheduler: &Scheduler,
+    ) -> bool {
+        while self.skip_empty_frame(emulator, scheduler) {}
+
+        emulator.emulate_cycle(engine);
+        self.frame_cycles += 1;
+        if self.frame_cycles < self.frame_length(scheduler) {
+            return false;
+        }
+
+        self.end_frame(emulator);
+        true
+    }
+
+    /// Ends the current frame if it has no instructions, which happens
+    /// when `cpu_hz` is below `timer_hz`
+    fn skip_empty_frame(&mut self, emulator: &mut Chip8, scheduler: &Scheduler) -> bool {
+        if self.frame_length(scheduler) > 0 {
+            return false;
+        }
+
+        self.end_frame(emulator);
+        true
+    }
+
+    fn frame_length(&mut self, scheduler: &Scheduler) -> u32 {
+        *self
+            .frame_length
+            .get_or_insert_with(|| scheduler.frame_instructions(&mut self.frame_state))
+    }
+
+    /// Ticks the timers and the held keys
+    fn end_frame(&mut self, emulator: &mut Chip8) {
+        self.frame_cycles = 0;
+        self.frame_length = None;
+        emulator.decrement_timers();
+        for (key, frames) in self.held_keys.iter_mut().enumerate() {
+            if *frames > 0 {
+                *frames -= 1;
+                emulator.set_key(key as u8, *frames > 0);
+            }
+        }
+    }
+}
+
+/// `before` instructions before PC and `after` from it on, as address and text.
+/// Instructions are assumed to be aligned with PC.
+pub fn disassembly_around(emulator: &Chip8, before: usize, after: usize) -> Vec<(u16, String)> {
+    let pc = emulator.pc() as usize;
+    let start = pc - before.min(pc / 2) * 2;
+
+    (start..emulator.memory().len() - 1)
+        .step_by(2)
+        .take(pc.saturating_sub(start) / 2 + after)
+        .map(|address| (address as u16, disassemble_at(emulator.memory(), address)))
+        .collect()
+}
+
+/// `rows` lines of `bytes_per_row` bytes from `start`, like `0200  00 E0 A2 2A`
+pub fn hex_view(memory: &[u8], start: u16, rows: usize, bytes_per_row: usize) -> Vec<String> {
+    (0..rows)
+        .map(|row| start as usize + row * bytes_per_row)
+        .take_while(|&address| address < memory.len())
+        .map(|address| {
+            let end = (address + bytes_per_row).min(memory.len());
+            let bytes: Vec<String> = memory[address..end]
+                .iter()
+                .map(|byte| format!("{:02X}", byte))
+                .collect();
+            format!("{:04X}  {}", address, bytes.join(" "))
+        })
+        .collect()
+}
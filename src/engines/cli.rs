@@ -1,22 +1,37 @@
 use std::{
-    io::{stdout, Stdout, Write},
+    io::{Stdout, Write},
     sync::{atomic, Arc},
+    time::{Duration, Instant},
 };
 
 use crossterm::{
     cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    style::{ResetColor, SetBackgroundColor, SetForegroundColor},
     terminal::{self, Clear, ClearType},
-    ExecutableCommand,
+    QueueableCommand,
 };
 
-use crate::{Chip8, Engine, SimpleRng};
+use crate::{Chip8, EmulationControl, Engine, FrameState, Palette, Recorder, Scheduler};
 
-use super::PixelBuf;
+use super::{terminal::TerminalGuard, Cell, ColorDepth, PixelBuf, TermDiff, TermRender};
+
+/// Terminals only report key presses and repeats, so turbo stays on
+/// for a while after the last one
+const TURBO_HOLD: Duration = Duration::from_millis(600);
 
 pub struct CliEngine {
     pbuf: PixelBuf,
     stdout: Stdout,
-    rng: SimpleRng,
+    render: TermRender,
+    palette: Palette,
+    colors: ColorDepth,
+    diff: TermDiff<Cell>,
+    /// Terminal size in columns and rows
+    size: (u16, u16),
+    control: EmulationControl,
+    turbo_until: Option<Instant>,
+    recorder: Option<Recorder>,
 }
 
 impl CliEngine {
@@ -24,13 +39,160 @@ impl CliEngine {
         CliEngine {
             pbuf: PixelBuf::new(),
             stdout,
-            rng: SimpleRng::new(),
+            render: TermRender::default(),
+            palette: Palette::default(),
+            colors: ColorDepth::detect(),
+            diff: TermDiff::new(),
+            size: (0, 0),
+            control: EmulationControl::default(),
+            turbo_until: None,
+            recorder: None,
+        }
+    }
+
+    pub fn set_render(&mut self, render: TermRender) {
+        self.render = render;
+        self.diff.invalidate();
+    }
+
+    /// `ColorDepth::Mono` ignores the palette and draws with the terminal colors
+    pub fn set_colors(&mut self, palette: Palette, colors: ColorDepth) {
+        self.palette = palette;
+        self.colors = colors;
+        self.diff.invalidate();
+    }
+
+    /// Captures every emulated frame until the loop ends
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Returns `false` when the user asked to quit.
+    ///
+    /// P pauses, N advances one frame, - and = change speed,
+    /// 0 resets it and Tab runs at full speed while held
+    fn handle_events(&mut self) -> bool {
+        while event::poll(Duration::ZERO).unwrap() {
+            let (code, modifiers) = match event::read().unwrap() {
+                Event::Key(KeyEvent {
+                    code, modifiers, ..
+                }) => (code, modifiers),
+                Event::Resize(columns, rows) => {
+                    self.resize(columns, rows);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            match code {
+                KeyCode::Esc => return false,
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return false,
+                KeyCode::Char('p') => self.control.toggle_pause(),
+                KeyCode::Char('n') => self.control.frame_advance(),
+                KeyCode::Char('-') => self.control.slower(),
+                KeyCode::Char('=') => self.control.faster(),
+                KeyCode::Char('0') => self.control.reset_speed(),
+                KeyCode::Tab => self.turbo_until = Some(Instant::now() + TURBO_HOLD),
+                _ => {}
+            }
+        }
+
+        let turbo = self.turbo_until.is_some_and(|until| Instant::now() < until);
+        self.control.set_turbo(turbo);
+
+        true
+    }
+
+    /// The terminal is cleared so everything gets drawn again
+    fn resize(&mut self, columns: u16, rows: u16) {
+        self.size = (columns, rows);
+        self.stdout.queue(Clear(ClearType::All)).unwrap();
+        self.diff.invalidate();
+    }
+
+    /// Columns and rows needed for the screen and the status line
+    fn required_size(&self) -> (u16, u16) {
+        let (cell_width, _) = self.render.cell_size();
+        let columns = self.pbuf.width().div_ceil(cell_width);
+        let rows = self.render.rows(&self.pbuf) + 1;
+
+        (columns as u16, rows as u16)
+    }
+
+    /// Prints the cells that changed since the last frame and the status
+    /// on the row below the screen, flushed as one write
+    fn present(&mut self) {
+        let (columns, rows) = self.required_size();
+        if self.size.0 < columns || self.size.1 < rows {
+            self.stdout
+                .queue(cursor::MoveTo(0, 0))
+                .unwrap()
+                .queue(Clear(ClearType::CurrentLine))
+                .unwrap();
+            write!(
+                self.stdout,
+                "terminal too small, needs {}x{}",
+                columns, rows
+            )
+            .unwrap();
+            self.stdout.flush().unwrap();
+            self.diff.invalidate();
+            return;
+        }
+
+        let cells = match self.colors {
+            ColorDepth::Mono => self
+                .render
+                .lines(&self.pbuf)
+                .iter()
+                .map(|line| {
+                    line.chars()
+                        .map(|ch| Cell {
+                            ch,
+                            foreground: 0,
+                            background: 0,
+                        })
+                        .collect()
+                })
+                .collect(),
+            _ => self.render.cells(&self.pbuf, &self.palette),
+        };
+
+        // colors of the last printed cell, to skip redundant escape codes
+        let mut style = None;
+        for change in self.diff.cell_changes(&cells) {
+            // raw mode: a newline does not return the cursor to the first column
+            self.stdout
+                .queue(cursor::MoveTo(change.column as u16, change.row as u16))
+                .unwrap();
+            for cell in change.cells {
+                if style != Some((cell.foreground, cell.background)) {
+                    style = Some((cell.foreground, cell.background));
+                    if let Some(color) = self.colors.color(cell.foreground) {
+                        self.stdout.queue(SetForegroundColor(color)).unwrap();
+                    }
+                    if let Some(color) = self.colors.color(cell.background) {
+                        self.stdout.queue(SetBackgroundColor(color)).unwrap();
+                    }
+                }
+                write!(self.stdout, "{}", cell.ch).unwrap();
+            }
         }
+        self.stdout.queue(ResetColor).unwrap();
+
+        let row = self.render.rows(&self.pbuf) as u16;
+        self.stdout
+            .queue(cursor::MoveTo(0, row))
+            .unwrap()
+            .queue(Clear(ClearType::CurrentLine))
+            .unwrap();
+        write!(self.stdout, "[{}]", self.control).unwrap();
+        self.stdout.flush().unwrap();
     }
 }
 
 impl Engine for CliEngine {
-    fn start_loop(&mut self, emulator: &mut Chip8) {
+    fn start_loop(&mut self, emulator: &mut Chip8, scheduler: &Scheduler) {
         let exit = Arc::new(atomic::AtomicBool::new(false));
         let r = exit.clone();
 
@@ -39,59 +201,38 @@ impl Engine for CliEngine {
         })
         .unwrap();
 
-        let mut stdout = stdout();
-        stdout
-            .execute(Clear(ClearType::All))
-            .unwrap()
-            .execute(terminal::SetSize(64, 32))
-            .unwrap()
-            .execute(cursor::MoveTo(0, 0))
-            .unwrap();
-
-        let cpu_sleep = std::time::Duration::from_secs(1) / 600;
-        let timers_sleep = std::time::Duration::from_secs(1) / 60;
-        let cpu_iterations_before_timers = timers_sleep.as_nanos() / cpu_sleep.as_nanos();
-
-        while !exit.load(atomic::Ordering::SeqCst) {
-            for _ in 0..cpu_iterations_before_timers {
-                emulator.emulate_cycle(self);
-                std::thread::sleep(cpu_sleep);
+        let guard = TerminalGuard::enter().unwrap();
+        let (columns, rows) = terminal::size().unwrap();
+        self.resize(columns, rows);
+
+        let mut pacer = scheduler.pacer();
+        let mut state = FrameState::default();
+        while !exit.load(atomic::Ordering::SeqCst) && self.handle_events() {
+            for _ in 0..self.control.frames_to_run(pacer.wait()) {
+                scheduler.run_frame(&mut state, emulator, self);
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.capture(&self.pbuf).unwrap();
+                }
             }
+            self.present();
+        }
 
-            emulator.decrement_timers();
+        drop(guard);
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish().unwrap();
         }
     }
 
     fn clear_screen(&mut self) {
-        self.stdout.execute(Clear(ClearType::All)).unwrap();
         self.pbuf.clear();
     }
 
     fn draw_sprite(&mut self, x: u8, y: u8, height: u8, sprite: &[u8]) -> bool {
-        let flipped = self.pbuf.draw_sprite(x, y, height, sprite);
-
-        self.stdout
-            // .execute(Clear(ClearType::All))
-            // .unwrap()
-            .execute(cursor::MoveTo(0, 0))
-            .unwrap();
-        for row in self.pbuf.gfx {
-            for pixel in row {
-                if pixel {
-                    write!(self.stdout, "#").unwrap();
-                } else {
-                    write!(self.stdout, " ").unwrap();
-                }
-            }
-            writeln!(self.stdout).unwrap();
-        }
-
-        self.stdout.flush().unwrap();
-
-        flipped
+        // shown by `present` at the end of the frame
+        self.pbuf.draw_sprite(x, y, height, sprite)
     }
 
-    fn rand(&mut self) -> u8 {
-        self.rng.next()
+    fn framebuffer(&self) -> &PixelBuf {
+        &self.pbuf
     }
 }
@@ -0,0 +1,123 @@
+use std::str::FromStr;
+
+use crossterm::style::Color;
+
+/// The 16 ANSI colors as xterm shows them, in index order
+const ANSI16: [u32; 16] = [
+    0x00_00_00, 0x80_00_00, 0x00_80_00, 0x80_80_00, 0x00_00_80, 0x80_00_80, 0x00_80_80, 0xC0_C0_C0,
+    0x80_80_80, 0xFF_00_00, 0x00_FF_00, 0xFF_FF_00, 0x00_00_FF, 0xFF_00_FF, 0x00_FF_FF, 0xFF_FF_FF,
+];
+
+/// Channel values of the 6x6x6 cube in the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Colors the terminal can show, palettes are approximated with the nearest available color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// Characters only, the terminal keeps its own colors
+    Mono,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+impl ColorDepth {
+    /// Guesses from the environment: `NO_COLOR`, `COLORTERM` and `TERM`
+    pub fn detect() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+
+        if std::env::var_os("NO_COLOR").is_some() || var("TERM") == "dumb" {
+            ColorDepth::Mono
+        } else if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit") {
+            ColorDepth::TrueColor
+        } else if var("TERM").contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        }
+    }
+
+    /// Terminal color closest to `0xRRGGBB`, `None` in mono
+    pub fn color(&self, rgb: u32) -> Option<Color> {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        match self {
+            ColorDepth::Mono => None,
+            ColorDepth::Ansi16 => Some(ansi16_color(ansi16(rgb))),
+            ColorDepth::Ansi256 => Some(Color::AnsiValue(ansi256(rgb))),
+            ColorDepth::TrueColor => Some(Color::Rgb { r, g, b }),
+        }
+    }
+}
+
+impl FromStr for ColorDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorDepth::detect()),
+            "none" => Ok(ColorDepth::Mono),
+            "16" => Ok(ColorDepth::Ansi16),
+            "256" => Ok(ColorDepth::Ansi256),
+            "truecolor" => Ok(ColorDepth::TrueColor),
+            _ => Err("unknown color depth, expected auto, none, 16, 256 or truecolor".to_owned()),
+        }
+    }
+}
+
+fn distance(a: u32, b: u32) -> u32 {
+    let [_, ar, ag, ab] = a.to_be_bytes();
+    let [_, br, bg, bb] = b.to_be_bytes();
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+
+    d(ar, br) + d(ag, bg) + d(ab, bb)
+}
+
+/// Index of the closest of the 16 ANSI colors
+pub fn ansi16(rgb: u32) -> u8 {
+    (0..16).min_by_key(|&i| distance(rgb, ANSI16[i])).unwrap() as u8
+}
+
+/// Index of the closest color in the 256 color palette, from the cube or the gray ramp
+pub fn ansi256(rgb: u32) -> u8 {
+    let level = |c: u8| {
+        (0..6)
+            .min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs())
+            .unwrap()
+    };
+    let [_, r, g, b] = rgb.to_be_bytes();
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = u32::from_be_bytes([0, CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]]);
+
+    // grays are 8, 18, .., 238
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let step = (average.saturating_sub(3) / 10).min(23);
+    let gray_level = (8 + step * 10) as u8;
+    let gray = u32::from_be_bytes([0, gray_level, gray_level, gray_level]);
+
+    if distance(rgb, gray) < distance(rgb, cube) {
+        232 + step as u8
+    } else {
+        16 + (36 * ri + 6 * gi + bi) as u8
+    }
+}
+
+fn ansi16_color(index: u8) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::DarkRed,
+        2 => Color::DarkGreen,
+        3 => Color::DarkYellow,
+        4 => Color::DarkBlue,
+        5 => Color::DarkMagenta,
+        6 => Color::DarkCyan,
+        7 => Color::Grey,
+        8 => Color::DarkGrey,
+        9 => Color::Red,
+        10 => Color::Green,
+        11 => Color::Yellow,
+        12 => Color::Blue,
+        13 => Color::Magenta,
+        14 => Color::Cyan,
+        _ => Color::White,
+    }
+}
@@ -0,0 +1,41 @@
+use crate::{Chip8, Engine, FrameState, Scheduler};
+
+use super::PixelBuf;
+
+/// Runs the emulator without any output and without sleeping,
+/// useful for tests and batch runs.
+pub struct HeadlessEngine {
+    pbuf: PixelBuf,
+    frames: usize,
+}
+
+impl HeadlessEngine {
+    /// `frames` is the number of frames `start_loop` emulates before returning
+    pub fn new(frames: usize) -> Self {
+        HeadlessEngine {
+            pbuf: PixelBuf::new(),
+            frames,
+        }
+    }
+}
+
+impl Engine for HeadlessEngine {
+    fn start_loop(&mut self, emulator: &mut Chip8, scheduler: &Scheduler) {
+        let mut state = FrameState::default();
+        for _ in 0..self.frames {
+            scheduler.run_frame(&mut state, emulator, self);
+        }
+    }
+
+    fn draw_sprite(&mut self, x: u8, y: u8, height: u8, sprite: &[u8]) -> bool {
+        self.pbuf.draw_sprite(x, y, height, sprite)
+    }
+
+    fn clear_screen(&mut self) {
+        self.pbuf.clear();
+    }
+
+    fn framebuffer(&self) -> &PixelBuf {
+        &self.pbuf
+    }
+}
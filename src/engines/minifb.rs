@@ -1,11 +1,71 @@
-use minifb::{self, Key, Window, WindowOptions};
+use minifb::{self, Key, KeyRepeat, Window, WindowOptions};
 
-use crate::{Chip8, Engine, SimpleRng};
+use crate::{
+    font::Canvas, save_png, Chip8, DisplayFilter, EmulationControl, Engine, FrameState, MemoryView,
+    Movie, Palette, Phosphor, Recorder, Scheduler, COLUMNS,
+};
 
-use super::PixelBuf;
+use super::{
+    panel::{DebugPanel, PANEL_WIDTH},
+    PixelBuf, Scaler,
+};
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
 
+const TITLE: &str = "Test - ESC to exit";
+
+/// Size of a CHIP-8 pixel in recordings started with F9
+const RECORDING_SCALE: u32 = 4;
+
+/// Size of a bitmap pixel in the F2 memory viewer
+const MEMORY_VIEW_SCALE: usize = 2;
+
+/// Keys typing hex digits 0-F into the memory viewer address
+const HEX_DIGITS: [Key; 16] = [
+    Key::Key0,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::Key7,
+    Key::Key8,
+    Key::Key9,
+    Key::A,
+    Key::B,
+    Key::C,
+    Key::D,
+    Key::E,
+    Key::F,
+];
+
+/// Keyboard keys for keypad keys 0-F, laid out as
+/// ```text
+/// 1 2 3 C      1 2 3 4
+/// 4 5 6 D  ->  Q W E R
+/// 7 8 9 E      A S D F
+/// A 0 B F      Z X C V
+/// ```
+const KEYPAD: [Key; 16] = [
+    Key::X,
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Q,
+    Key::W,
+    Key::E,
+    Key::A,
+    Key::S,
+    Key::D,
+    Key::Z,
+    Key::C,
+    Key::Key4,
+    Key::R,
+    Key::F,
+    Key::V,
+];
+
 pub struct MinifbEngine {
     pbuf: PixelBuf,
     buffer: Vec<u32>,
@@ -13,7 +73,20 @@ pub struct MinifbEngine {
     height: usize,
     scale: usize,
     window: Window,
-    rng: SimpleRng,
+    palette: Palette,
+    scaler: Scaler,
+    filter: DisplayFilter,
+    phosphor: Phosphor,
+    control: EmulationControl,
+    /// Control state currently shown in the title
+    status: String,
+    movie: Option<Movie>,
+    recorder: Option<Recorder>,
+    panel: Option<DebugPanel>,
+    /// Replaces the game screen while open, toggled with F2
+    memory_view: Option<MemoryView>,
+    /// Hex address typed into the memory viewer
+    address: String,
 }
 
 impl MinifbEngine {
@@ -21,81 +94,336 @@ impl MinifbEngine {
         let width = 64 * scale;
         let height = 32 * scale;
 
-        let mut window = Window::new(
-            "Test - ESC to exit",
-            width,
-            height,
-            WindowOptions::default(),
-        )?;
+        let options = WindowOptions {
+            resize: true,
+            // the buffer always matches the window size, see `handle_resize`
+            scale_mode: minifb::ScaleMode::UpperLeft,
+            ..WindowOptions::default()
+        };
+        let mut window = Window::new(TITLE, width, height, options)?;
 
-        // Limit to max ~60 fps update rate
-        window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+        // frames are paced by the scheduler
+        window.limit_update_rate(None);
 
         Ok(MinifbEngine {
-            rng: SimpleRng::new(),
             buffer: vec![0; width * height],
             pbuf: PixelBuf::new(),
             height,
             width,
             scale,
             window,
+            palette: Palette::default(),
+            scaler: Scaler::default(),
+            filter: DisplayFilter::default(),
+            phosphor: Phosphor::default(),
+            control: EmulationControl::default(),
+            status: String::new(),
+            movie: None,
+            recorder: None,
+            panel: None,
+            memory_view: None,
+            address: String::new(),
         })
     }
 
-    fn draw_to_window(&mut self) -> Result<()> {
-        for (y, row) in self.pbuf.gfx.iter().enumerate() {
-            for (x, pixel) in row.iter().enumerate() {
-                let color = if *pixel { 0xFF_FF_FF } else { 0x0 };
-                for x_offset in 2..self.scale - 2 {
-                    for y_offset in 2..self.scale - 2 {
-                        self.buffer[(x * self.scale)
-                            + x_offset
-                            + (y * self.scale * self.width)
-                            + self.width * y_offset] = color;
-                    }
-                }
+    /// Colors used for the window, screenshots and F9 recordings
+    pub fn set_palette(&mut self, palette: Palette) {
+        self.palette = palette;
+        self.buffer.fill(palette.background);
+        self.draw_to_window().unwrap();
+    }
+
+    pub fn set_scaler(&mut self, scaler: Scaler) {
+        self.scaler = scaler;
+        self.draw_to_window().unwrap();
+    }
+
+    pub fn set_display_filter(&mut self, filter: DisplayFilter) {
+        self.filter = filter;
+        self.phosphor = Phosphor::default();
+    }
+
+    /// Captures every emulated frame until the recording is stopped with F9 or the loop ends
+    pub fn record(&mut self, recorder: Recorder) {
+        self.recorder = Some(recorder);
+    }
+
+    /// Starts recording to the first free `recording-N.gif` or stops the current recording
+    fn toggle_recording(&mut self) -> Result<()> {
+        match self.recorder.take() {
+            Some(recorder) => {
+                recorder.finish()?;
+                println!("recording stopped");
+            }
+            None => {
+                let path = (1..)
+                    .map(|n| format!("recording-{}.gif", n))
+                    .find(|path| !std::path::Path::new(path).exists())
+                    .unwrap();
+
+                self.recorder = Some(Recorder::create(&path, RECORDING_SCALE, self.palette)?);
+                println!("recording to {}", path);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Records keypad input of every frame into `movie`
+    pub fn record_movie(&mut self, movie: Movie) {
+        self.movie = Some(movie);
+    }
+
+    /// Returns the recorded movie after `start_loop` finished
+    pub fn take_movie(&mut self) -> Option<Movie> {
+        self.movie.take()
+    }
+
+    /// Saves the screen to the first free `screenshot-N.png` in the working directory
+    fn save_screenshot(&self) -> Result<()> {
+        let path = (1..)
+            .map(|n| format!("screenshot-{}.png", n))
+            .find(|path| !std::path::Path::new(path).exists())
+            .unwrap();
+
+        save_png(&self.pbuf, self.scale as u32, &self.palette, &path)?;
+        println!("saved {}", path);
+
+        Ok(())
+    }
+
+    /// The keypad is released while the memory viewer uses the keyboard
+    fn update_keypad(&self, emulator: &mut Chip8) {
+        for (key, &keyboard_key) in KEYPAD.iter().enumerate() {
+            let down = self.memory_view.is_none() && self.window.is_key_down(keyboard_key);
+            emulator.set_key(key as u8, down);
+        }
+    }
+
+    /// P pauses, N advances one frame, - and = change speed,
+    /// 0 resets it and Tab runs at full speed while held,
+    /// F12 takes a screenshot, F9 starts or stops recording
+    /// and F1 shows or hides the debug panel.
+    ///
+    /// F2 opens the memory viewer, which takes the keyboard over,
+    /// see `handle_memory_keys`
+    fn handle_control_keys(&mut self, emulator: &Chip8) {
+        if self.window.is_key_pressed(Key::F2, KeyRepeat::No) {
+            self.memory_view = match self.memory_view {
+                Some(_) => None,
+                None => Some(MemoryView::new(MEMORY_VIEW_SCALE)),
+            };
+            self.address.clear();
+            self.buffer.fill(self.palette.background);
+            self.draw_to_window().unwrap();
+        }
+        if self.memory_view.is_some() {
+            self.handle_memory_keys(emulator);
+        } else {
+            self.handle_emulation_keys();
+        }
+
+        let pressed = |key| self.window.is_key_pressed(key, KeyRepeat::No);
+        if pressed(Key::F12) {
+            self.save_screenshot().unwrap();
+        }
+        if pressed(Key::F9) {
+            self.toggle_recording().unwrap();
+        }
+        if self.window.is_key_pressed(Key::F1, KeyRepeat::No) {
+            self.panel = match self.panel {
+                Some(_) => None,
+                None => Some(DebugPanel::new()),
+            };
+            self.buffer.fill(self.palette.background);
+            self.draw_to_window().unwrap();
+        }
+
+        let mut status = self.control.to_string();
+        if let Some(view) = &self.memory_view {
+            status += &format!("] [memory {:03X} > {}_", view.start(), self.address);
+        }
+        if status != self.status {
+            self.window.set_title(&format!("{} [{}]", TITLE, status));
+            self.status = status;
+        }
+    }
+
+    fn handle_emulation_keys(&mut self) {
+        let pressed = |key| self.window.is_key_pressed(key, KeyRepeat::No);
+
+        if pressed(Key::P) {
+            self.control.toggle_pause();
+        }
+        if self.window.is_key_pressed(Key::N, KeyRepeat::Yes) {
+            self.control.frame_advance();
+        }
+        if pressed(Key::Minus) {
+            self.control.slower();
+        }
+        if pressed(Key::Equal) {
+            self.control.faster();
+        }
+        if pressed(Key::Key0) {
+            self.control.reset_speed();
+        }
+        self.control.set_turbo(self.window.is_key_down(Key::Tab));
+    }
+
+    /// Hex digits and Backspace edit the address, Enter jumps to it
+    /// and I jumps to the I register. Up and Down scroll by a column,
+    /// Page Up and Page Down by a band of columns
+    fn handle_memory_keys(&mut self, emulator: &Chip8) {
+        let Some(view) = &mut self.memory_view else {
+            return;
+        };
+        let pressed = |key| self.window.is_key_pressed(key, KeyRepeat::Yes);
+
+        for (digit, &key) in HEX_DIGITS.iter().enumerate() {
+            if pressed(key) && self.address.len() < 3 {
+                self.address.push_str(&format!("{:X}", digit));
+            }
+        }
+        if pressed(Key::Backspace) {
+            self.address.pop();
+        }
+        if pressed(Key::Enter) {
+            if let Ok(address) = u16::from_str_radix(&self.address, 16) {
+                view.jump(address);
             }
+            self.address.clear();
+        }
+        if pressed(Key::I) {
+            view.jump(emulator.cpu_state().i);
+        }
+        if pressed(Key::Up) {
+            view.scroll(-1);
+        }
+        if pressed(Key::Down) {
+            view.scroll(1);
+        }
+        if pressed(Key::PageUp) {
+            view.scroll(-(COLUMNS as isize));
+        }
+        if pressed(Key::PageDown) {
+            view.scroll(COLUMNS as isize);
+        }
+    }
+
+    /// Resizes the buffer to the window and redraws
+    fn handle_resize(&mut self) -> Result<()> {
+        let (width, height) = self.window.get_size();
+        if (width, height) != (self.width, self.height) && width > 0 && height > 0 {
+            self.width = width;
+            self.height = height;
+            self.buffer = vec![self.palette.background; width * height];
+            self.draw_to_window()?;
+        }
+
+        Ok(())
+    }
+
+    /// Width of the game screen or memory viewer, left of the debug panel
+    fn screen_width(&self) -> usize {
+        match self.panel {
+            Some(_) => self.width.saturating_sub(PANEL_WIDTH),
+            None => self.width,
+        }
+    }
+
+    /// The memory viewer is drawn once per frame by `start_loop` instead
+    fn draw_to_window(&mut self) -> Result<()> {
+        if self.memory_view.is_some() {
+            return Ok(());
         }
 
+        let width = self.screen_width();
+        let pbuf = &self.pbuf;
+        let palette = &self.palette;
+        let phosphor = &self.phosphor;
+        let filter = self.filter;
+
+        self.scaler.render_region(
+            &mut self.buffer,
+            self.width,
+            width,
+            self.height,
+            palette.background,
+            |x, y| match filter {
+                DisplayFilter::Phosphor => phosphor.color(x, y, palette),
+                _ => palette.color(pbuf.pixel(x, y) as u8),
+            },
+        );
+
         Ok(())
     }
 }
 
 impl Engine for MinifbEngine {
-    fn start_loop(&mut self, emulator: &mut Chip8) {
-        let cpu_sleep = std::time::Duration::from_secs(1) / 600;
-        let timers_sleep = std::time::Duration::from_secs(1) / 60;
-        let cpu_iterations_before_timers = timers_sleep.as_nanos() / cpu_sleep.as_nanos();
-
+    fn start_loop(&mut self, emulator: &mut Chip8, scheduler: &Scheduler) {
+        let mut pacer = scheduler.pacer();
+        let mut state = FrameState::default();
         while self.window.is_open() && !self.window.is_key_down(Key::Escape) {
-            for _ in 0..cpu_iterations_before_timers {
-                emulator.emulate_cycle(self);
-                std::thread::sleep(cpu_sleep);
+            self.handle_control_keys(emulator);
+            self.handle_resize().unwrap();
+            self.update_keypad(emulator);
+            for _ in 0..self.control.frames_to_run(pacer.wait()) {
+                if let Some(movie) = &mut self.movie {
+                    movie.record_frame(emulator);
+                }
+                scheduler.run_frame(&mut state, emulator, self);
+                if self.filter == DisplayFilter::Phosphor {
+                    self.phosphor.update(&self.pbuf);
+                }
+                if let Some(recorder) = &mut self.recorder {
+                    recorder.capture(&self.pbuf).unwrap();
+                }
+            }
+            // presented once per loop, drawing on every sprite would never be seen
+            self.draw_to_window().unwrap();
+            if let Some(view) = &self.memory_view {
+                let width = self.screen_width();
+                let mut canvas = Canvas {
+                    buffer: &mut self.buffer,
+                    stride: self.width,
+                    left: 0,
+                    width,
+                    height: self.height,
+                };
+                view.render(emulator, &self.palette, &mut canvas);
+            }
+            if let Some(panel) = &mut self.panel {
+                panel.update(emulator);
+                panel.draw(
+                    emulator,
+                    &mut self.buffer,
+                    self.width,
+                    self.height,
+                    &self.palette,
+                );
             }
-
-            emulator.decrement_timers();
             self.window
                 .update_with_buffer(&self.buffer, self.width, self.height)
                 .unwrap();
         }
+
+        if let Some(movie) = &mut self.movie {
+            movie.finish(&self.pbuf);
+        }
+        if let Some(recorder) = self.recorder.take() {
+            recorder.finish().unwrap();
+        }
     }
 
     fn draw_sprite(&mut self, x: u8, y: u8, height: u8, sprite: &[u8]) -> bool {
-        let flipped = self.pbuf.draw_sprite(x, y, height, sprite);
-
-        self.draw_to_window().unwrap();
-
-        flipped
+        self.pbuf.draw_sprite(x, y, height, sprite)
     }
 
     fn clear_screen(&mut self) {
         self.pbuf.clear();
-        for el in self.buffer.iter_mut() {
-            *el = 0;
-        }
     }
 
-    fn rand(&mut self) -> u8 {
-        self.rng.next()
+    fn framebuffer(&self) -> &PixelBuf {
+        &self.pbuf
     }
 }
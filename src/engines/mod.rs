@@ -1,21 +1,63 @@
 mod cli;
 pub use cli::CliEngine;
 
+mod color;
+pub use color::{ansi16, ansi256, ColorDepth};
+
 mod minifb;
 pub use crate::engines::minifb::MinifbEngine;
 
-struct PixelBuf {
+mod panel;
+pub use panel::{DebugPanel, PANEL_WIDTH};
+
+mod headless;
+pub use headless::HeadlessEngine;
+
+mod scaler;
+pub use scaler::{ScaleMode, Scaler};
+
+mod terminal;
+
+mod tui;
+pub use tui::TuiEngine;
+
+mod term_render;
+pub use term_render::{Cell, CellChange, Change, TermDiff, TermRender};
+
+/// Monochrome framebuffer the engines draw sprites into
+pub struct PixelBuf {
     gfx: [[bool; 64]; 32],
 }
 
 impl PixelBuf {
-    fn new() -> Self {
+    pub const WIDTH: usize = 64;
+    pub const HEIGHT: usize = 32;
+
+    pub fn new() -> Self {
         Self {
             gfx: [[false; 64]; 32],
         }
     }
 
-    fn clear(&mut self) {
+    pub fn width(&self) -> usize {
+        Self::WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        Self::HEIGHT
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> bool {
+        self.gfx[y][x]
+    }
+
+    /// Stable hash of the pixels, used to check replays
+    pub fn hash(&self) -> u64 {
+        let bytes: Vec<u8> = self.gfx.iter().flatten().map(|&p| p as u8).collect();
+        crate::fnv1a(&bytes)
+    }
+
+    pub fn clear(&mut self) {
         for row in self.gfx.iter_mut() {
             for pixel in row {
                 *pixel = false;
@@ -23,10 +65,9 @@ impl PixelBuf {
         }
     }
 
-    fn draw_sprite(&mut self, x: u8, y: u8, height: u8, sprite: &[u8]) -> bool {
+    pub fn draw_sprite(&mut self, x: u8, y: u8, height: u8, sprite: &[u8]) -> bool {
         let mut flipped = false;
-        for yline in 0..height as usize {
-            let pixels = sprite[yline as usize];
+        for (yline, &pixels) in sprite.iter().take(height as usize).enumerate() {
             for xline in 0..8 {
                 let pixel = pixels & (0b1000_0000 >> xline) != 0;
                 if pixel {
@@ -44,3 +85,9 @@ impl PixelBuf {
         flipped
     }
 }
+
+impl Default for PixelBuf {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,145 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    disassemble_at,
+    font::{Canvas, CHAR_HEIGHT, CHAR_WIDTH},
+    Chip8, Palette,
+};
+
+/// Width of the panel in window pixels
+pub const PANEL_WIDTH: usize = 240;
+/// Size of a font pixel
+const SCALE: usize = 2;
+const MARGIN: usize = 8;
+/// How often FPS and IPS are recomputed
+const RATE_PERIOD: Duration = Duration::from_millis(500);
+
+/// Side panel of `MinifbEngine` showing the CPU state, toggled with F1
+pub struct DebugPanel {
+    since: Instant,
+    frames: u32,
+    /// Instruction count at `since`, unknown until the first update
+    cycles: Option<u64>,
+    fps: f32,
+    ips: f32,
+}
+
+impl Default for DebugPanel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugPanel {
+    pub fn new() -> Self {
+        DebugPanel {
+            since: Instant::now(),
+            frames: 0,
+            cycles: None,
+            fps: 0.0,
+            ips: 0.0,
+        }
+    }
+
+    /// Call once per presented frame
+    pub fn update(&mut self, emulator: &Chip8) {
+        let Some(cycles) = self.cycles else {
+            self.since = Instant::now();
+            self.cycles = Some(emulator.cycles());
+            return;
+        };
+        self.frames += 1;
+
+        let elapsed = self.since.elapsed();
+        if elapsed >= RATE_PERIOD {
+            let seconds = elapsed.as_secs_f32();
+            self.fps = self.frames as f32 / seconds;
+            self.ips = (emulator.cycles() - cycles) as f32 / seconds;
+
+            self.since = Instant::now();
+            self.frames = 0;
+            self.cycles = Some(emulator.cycles());
+        }
+    }
+
+    /// Text of the panel, one entry per row
+    pub fn lines(&self, emulator: &Chip8) -> Vec<String> {
+        let state = emulator.cpu_state();
+
+        let mut lines = vec![
+            format!("PC {:04X}  I {:04X}", state.pc, state.i),
+            format!(
+                "DT {:02X}  ST {:02X}  SP {:X}",
+                state.delay_timer, state.sound_timer, state.sp
+            ),
+            String::new(),
+        ];
+        for (row, registers) in state.v.chunks(4).enumerate() {
+            let registers: Vec<String> = registers
+                .iter()
+                .enumerate()
+                .map(|(i, v)| format!("V{:X} {:02X}", row * 4 + i, v))
+                .collect();
+            lines.push(registers.join(" "));
+        }
+
+        lines.push(String::new());
+        lines.push(format!(
+            "> {}",
+            disassemble_at(emulator.memory(), state.pc as usize)
+        ));
+
+        lines.push(String::new());
+        lines.push("STACK".to_owned());
+        let stack = &state.stack[..state.sp as usize];
+        for (row, pair) in stack.chunks(2).enumerate() {
+            let pair: Vec<String> = pair
+                .iter()
+                .enumerate()
+                .map(|(i, address)| format!("{:X} {:04X}", row * 2 + i, address))
+                .collect();
+            lines.push(pair.join("   "));
+        }
+
+        lines.push(String::new());
+        lines.push(format!("FPS {:.0}  IPS {:.0}", self.fps, self.ips));
+
+        lines
+    }
+
+    /// Draws the panel over the `PANEL_WIDTH` rightmost columns of `buffer`
+    pub fn draw(
+        &self,
+        emulator: &Chip8,
+        buffer: &mut [u32],
+        width: usize,
+        height: usize,
+        palette: &Palette,
+    ) {
+        let left = width.saturating_sub(PANEL_WIDTH);
+        let mut canvas = Canvas {
+            buffer,
+            stride: width,
+            left,
+            width: width - left,
+            height,
+        };
+
+        canvas.fill_rect(0, 0, canvas.width, height, palette.background);
+        // separator
+        canvas.fill_rect(0, 0, SCALE, height, palette.plane2);
+
+        let line_height = (CHAR_HEIGHT + 1) * SCALE;
+        for (row, line) in self.lines(emulator).iter().enumerate() {
+            let max_chars = (PANEL_WIDTH - 2 * MARGIN) / (CHAR_WIDTH * SCALE);
+            let line: String = line.chars().take(max_chars).collect();
+            canvas.text(
+                MARGIN,
+                MARGIN + row * line_height,
+                &line,
+                palette.foreground,
+                SCALE,
+            );
+        }
+    }
+}
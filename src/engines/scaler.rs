@@ -0,0 +1,143 @@
+use std::str::FromStr;
+
+use super::PixelBuf;
+
+/// How the 64x32 picture is fitted into the window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScaleMode {
+    /// Largest whole number of window pixels per CHIP-8 pixel, so every pixel has the same size
+    #[default]
+    Integer,
+    /// Fill as much of the window as the 2:1 aspect ratio allows
+    Aspect,
+}
+
+impl FromStr for ScaleMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "integer" => Ok(ScaleMode::Integer),
+            "aspect" => Ok(ScaleMode::Aspect),
+            _ => Err("unknown scaling, expected integer or aspect".to_owned()),
+        }
+    }
+}
+
+/// Draws the CHIP-8 pixels into a window buffer of any size, centered
+/// with `background` around them
+#[derive(Debug, Clone)]
+pub struct Scaler {
+    pub mode: ScaleMode,
+    /// Background pixels between neighbouring cells, 0 turns the grid off.
+    /// Cells too small to fit the gap are drawn without one.
+    pub gap: usize,
+    /// Darken every other row like a CRT
+    pub scanlines: bool,
+}
+
+impl Default for Scaler {
+    fn default() -> Self {
+        Scaler {
+            mode: ScaleMode::Integer,
+            gap: 4,
+            scanlines: false,
+        }
+    }
+}
+
+impl Scaler {
+    /// Position and size of the picture inside a `width`x`height` window
+    pub fn viewport(&self, width: usize, height: usize) -> (usize, usize, usize, usize) {
+        let (w, h) = match self.mode {
+            ScaleMode::Integer => {
+                let scale = (width / PixelBuf::WIDTH)
+                    .min(height / PixelBuf::HEIGHT)
+                    .max(1);
+                (PixelBuf::WIDTH * scale, PixelBuf::HEIGHT * scale)
+            }
+            ScaleMode::Aspect => {
+                let w = width.min(height * 2).max(PixelBuf::WIDTH);
+                (w, w / 2)
+            }
+        };
+
+        (
+            width.saturating_sub(w) / 2,
+            height.saturating_sub(h) / 2,
+            w,
+            h,
+        )
+    }
+
+    /// Fills `buffer`, `color(x, y)` is the `0xRRGGBB` of CHIP-8 pixel `x`, `y`
+    pub fn render(
+        &self,
+        buffer: &mut [u32],
+        width: usize,
+        height: usize,
+        background: u32,
+        color: impl Fn(usize, usize) -> u32,
+    ) {
+        self.render_region(buffer, width, width, height, background, color);
+    }
+
+    /// Like `render` but only fills the `width` leftmost columns of a buffer
+    /// that is `stride` pixels wide, leaving room for something else
+    pub fn render_region(
+        &self,
+        buffer: &mut [u32],
+        stride: usize,
+        width: usize,
+        height: usize,
+        background: u32,
+        color: impl Fn(usize, usize) -> u32,
+    ) {
+        for row in buffer.chunks_mut(stride).take(height) {
+            row[..width.min(stride)].fill(background);
+        }
+
+        let (left, top, w, h) = self.viewport(width, height);
+        // every cell must keep at least one lit pixel per side
+        let gap = if self.gap < w / PixelBuf::WIDTH && self.gap < h / PixelBuf::HEIGHT {
+            self.gap
+        } else {
+            0
+        };
+
+        // first window pixel of each cell along both axes
+        let cell_x = |x: usize| x * w / PixelBuf::WIDTH;
+        let cell_y = |y: usize| y * h / PixelBuf::HEIGHT;
+
+        for y in 0..PixelBuf::HEIGHT {
+            let rows = cell_y(y) + gap / 2..cell_y(y + 1) - (gap - gap / 2);
+            for x in 0..PixelBuf::WIDTH {
+                let columns = cell_x(x) + gap / 2..cell_x(x + 1) - (gap - gap / 2);
+                let color = color(x, y);
+                if color == background {
+                    continue;
+                }
+
+                // clipped to windows smaller than the picture
+                let begin = left + columns.start;
+                let end = (left + columns.end).min(width);
+                for row in rows.clone().filter(|row| top + row < height) {
+                    let row_color = if self.scanlines && row % 2 == 1 {
+                        dim(color)
+                    } else {
+                        color
+                    };
+                    let start = (top + row) * stride;
+                    if begin < end {
+                        buffer[start + begin..start + end].fill(row_color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Half the brightness of `0xRRGGBB`
+fn dim(color: u32) -> u32 {
+    (color >> 1) & 0x7F_7F_7F
+}
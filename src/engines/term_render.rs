@@ -0,0 +1,249 @@
+use std::{ops::Range, str::FromStr};
+
+use crate::Palette;
+
+use super::PixelBuf;
+
+/// How `CliEngine` turns pixels into characters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TermRender {
+    /// One `#` per pixel, terminal cells are about twice as tall as wide so it looks stretched
+    Ascii,
+    /// `▀`, `▄` and `█`, two pixels stacked in every cell
+    #[default]
+    HalfBlock,
+    /// Braille patterns, 2x4 pixels in every cell
+    Braille,
+}
+
+impl FromStr for TermRender {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ascii" => Ok(TermRender::Ascii),
+            "half-block" => Ok(TermRender::HalfBlock),
+            "braille" => Ok(TermRender::Braille),
+            _ => Err("unknown terminal renderer, expected ascii, half-block or braille".to_owned()),
+        }
+    }
+}
+
+/// A character with its colors as `0xRRGGBB`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub foreground: u32,
+    pub background: u32,
+}
+
+/// Braille dot of each pixel in a 2x4 cell, indexed by `[y][x]`
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+impl TermRender {
+    /// Pixels covered by one character, `(width, height)`
+    pub fn cell_size(&self) -> (usize, usize) {
+        match self {
+            TermRender::Ascii => (1, 1),
+            TermRender::HalfBlock => (1, 2),
+            TermRender::Braille => (2, 4),
+        }
+    }
+
+    /// Terminal rows needed for `framebuffer`
+    pub fn rows(&self, framebuffer: &PixelBuf) -> usize {
+        let (_, cell_height) = self.cell_size();
+        framebuffer.height().div_ceil(cell_height)
+    }
+
+    /// Character for the cell whose top left pixel is `x`, `y`
+    pub fn cell(&self, framebuffer: &PixelBuf, x: usize, y: usize) -> char {
+        let pixel = |dx: usize, dy: usize| {
+            x + dx < framebuffer.width()
+                && y + dy < framebuffer.height()
+                && framebuffer.pixel(x + dx, y + dy)
+        };
+
+        match self {
+            TermRender::Ascii => {
+                if pixel(0, 0) {
+                    '#'
+                } else {
+                    ' '
+                }
+            }
+            TermRender::HalfBlock => match (pixel(0, 0), pixel(0, 1)) {
+                (true, true) => '█',
+                (true, false) => '▀',
+                (false, true) => '▄',
+                (false, false) => ' ',
+            },
+            TermRender::Braille => {
+                let mut dots = 0;
+                for (dy, row) in BRAILLE_DOTS.iter().enumerate() {
+                    for (dx, dot) in row.iter().enumerate() {
+                        if pixel(dx, dy) {
+                            dots |= dot;
+                        }
+                    }
+                }
+                char::from_u32(0x2800 + dots).unwrap()
+            }
+        }
+    }
+
+    /// Colored cells of every terminal row. Half-blocks always print `▀`
+    /// with the top pixel as foreground and the bottom one as background,
+    /// so both pixels can have any palette color.
+    pub fn cells(&self, framebuffer: &PixelBuf, palette: &Palette) -> Vec<Vec<Cell>> {
+        let (cell_width, cell_height) = self.cell_size();
+        let color = |x: usize, y: usize| {
+            let lit = y < framebuffer.height() && framebuffer.pixel(x, y);
+            palette.color(lit as u8)
+        };
+
+        (0..self.rows(framebuffer))
+            .map(|row| {
+                let y = row * cell_height;
+                (0..framebuffer.width())
+                    .step_by(cell_width)
+                    .map(|x| match self {
+                        TermRender::HalfBlock => Cell {
+                            ch: '▀',
+                            foreground: color(x, y),
+                            background: color(x, y + 1),
+                        },
+                        _ => Cell {
+                            ch: self.cell(framebuffer, x, y),
+                            foreground: palette.foreground,
+                            background: palette.background,
+                        },
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// One string per terminal row
+    pub fn lines(&self, framebuffer: &PixelBuf) -> Vec<String> {
+        let (cell_width, cell_height) = self.cell_size();
+
+        (0..self.rows(framebuffer))
+            .map(|row| {
+                (0..framebuffer.width())
+                    .step_by(cell_width)
+                    .map(|x| self.cell(framebuffer, x, row * cell_height))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Unchanged cells between two changes that are cheaper to reprint than to skip
+/// with a cursor move, which takes at least 6 bytes
+const MERGE_GAP: usize = 4;
+
+/// Text to print at a position, `column` counts characters
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub row: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+/// Colored cells to print at a position
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellChange {
+    pub row: usize,
+    pub column: usize,
+    pub cells: Vec<Cell>,
+}
+
+/// Remembers what is on the terminal so only changed cells get printed.
+/// Cells are plain characters or [`Cell`]s when colors matter.
+#[derive(Debug)]
+pub struct TermDiff<T = char> {
+    presented: Vec<Vec<T>>,
+}
+
+impl<T> Default for TermDiff<T> {
+    fn default() -> Self {
+        TermDiff {
+            presented: Vec::new(),
+        }
+    }
+}
+
+impl<T: Clone + PartialEq> TermDiff<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets the presented frame, the next `changes` redraws everything
+    pub fn invalidate(&mut self) {
+        self.presented.clear();
+    }
+
+    /// Runs of cells that differ from the last call as row and columns,
+    /// `rows` becomes the presented frame
+    fn runs(&mut self, rows: Vec<Vec<T>>) -> Vec<(usize, Range<usize>)> {
+        let mut runs = Vec::new();
+
+        for (row, line) in rows.iter().enumerate() {
+            let previous = self.presented.get(row);
+            let changed =
+                |column: usize| previous.and_then(|p| p.get(column)) != Some(&line[column]);
+
+            let mut column = 0;
+            while column < line.len() {
+                if !changed(column) {
+                    column += 1;
+                    continue;
+                }
+
+                let start = column;
+                let mut end = column + 1;
+                // extend over short unchanged gaps
+                while let Some(next) =
+                    (end..line.len().min(end + MERGE_GAP + 1)).find(|&c| changed(c))
+                {
+                    end = next + 1;
+                }
+
+                runs.push((row, start..end));
+                column = end;
+            }
+        }
+
+        self.presented = rows;
+        runs
+    }
+}
+
+impl TermDiff<char> {
+    /// Runs of cells that differ from the last call, `lines` becomes the presented frame
+    pub fn changes(&mut self, lines: &[String]) -> Vec<Change> {
+        let runs = self.runs(lines.iter().map(|line| line.chars().collect()).collect());
+        runs.into_iter()
+            .map(|(row, columns)| Change {
+                row,
+                column: columns.start,
+                text: self.presented[row][columns].iter().collect(),
+            })
+            .collect()
+    }
+}
+
+impl TermDiff<Cell> {
+    /// `changes` for colored cells
+    pub fn cell_changes(&mut self, rows: &[Vec<Cell>]) -> Vec<CellChange> {
+        let runs = self.runs(rows.to_vec());
+        runs.into_iter()
+            .map(|(row, columns)| CellChange {
+                row,
+                column: columns.start,
+                cells: rows[row][columns].to_vec(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,63 @@
+use std::{
+    io::{self, stdout},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Once,
+    },
+};
+
+use crossterm::{
+    cursor,
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+    ExecutableCommand,
+};
+
+/// Set while a guard owns the terminal, so the panic hook knows whether to restore it
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+
+/// Switches to the alternate screen in raw mode with a hidden cursor
+/// and puts the terminal back when dropped or when the program panics.
+///
+/// Ctrl-C arrives as a key in raw mode and SIGTERM through `ctrlc`,
+/// both end the loop normally so the guard gets dropped.
+pub(super) struct TerminalGuard;
+
+impl TerminalGuard {
+    pub(super) fn enter() -> io::Result<Self> {
+        PANIC_HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                // the message would be lost on the alternate screen
+                restore();
+                previous(info);
+            }));
+        });
+
+        ACTIVE.store(true, Ordering::SeqCst);
+        // needed to read keys as they are pressed
+        terminal::enable_raw_mode()?;
+        stdout()
+            .execute(EnterAlternateScreen)?
+            .execute(cursor::Hide)?
+            .execute(Clear(ClearType::All))?;
+
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Undoes `TerminalGuard::enter`, only the first call after it does anything
+fn restore() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let mut stdout = stdout();
+        let _ = stdout.execute(cursor::Show);
+        let _ = stdout.execute(LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    io::{Stdout, Write},
+    sync::{atomic, Arc},
+    time::Duration,
+};
+
+use crossterm::{
+    cursor,
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    terminal::{self, Clear, ClearType},
+    QueueableCommand,
+};
+
+use crate::{disassembly_around, hex_view, Chip8, Command, Debugger, Engine, Scheduler};
+
+use super::{terminal::TerminalGuard, PixelBuf, TermDiff, TermRender};
+
+/// Width of the disassembly pane including its border
+const DISASSEMBLY_WIDTH: usize = 36;
+/// Bytes per row of the memory pane
+const HEX_BYTES: usize = 8;
+
+/// Debugger dashboard for terminals: the screen, disassembly around PC,
+/// registers and stack, a memory view and a command line, see [`Command`].
+///
+/// Starts paused, `c` + Enter runs the game. Enter on an empty line
+/// repeats the last command and Esc clears the line or pauses.
+pub struct TuiEngine {
+    pbuf: PixelBuf,
+    stdout: Stdout,
+    render: TermRender,
+    diff: TermDiff,
+    /// Terminal size in columns and rows
+    size: (u16, u16),
+    /// Command being typed
+    input: String,
+    last_command: Option<Command>,
+    /// Error of the last command
+    message: String,
+}
+
+impl TuiEngine {
+    pub fn new(stdout: Stdout) -> Self {
+        TuiEngine {
+            pbuf: PixelBuf::new(),
+            stdout,
+            render: TermRender::default(),
+            diff: TermDiff::new(),
+            size: (0, 0),
+            input: String::new(),
+            last_command: None,
+            message: String::new(),
+        }
+    }
+
+    pub fn set_render(&mut self, render: TermRender) {
+        self.render = render;
+        self.diff.invalidate();
+    }
+
+    /// Edits the command line, returns the commands entered
+    fn handle_events(&mut self, debugger: &Debugger) -> Vec<Command> {
+        let mut commands = Vec::new();
+        while event::poll(Duration::ZERO).unwrap() {
+            let (code, modifiers) = match event::read().unwrap() {
+                Event::Key(KeyEvent {
+                    code, modifiers, ..
+                }) => (code, modifiers),
+                Event::Resize(columns, rows) => {
+                    self.resize(columns, rows);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            match code {
+                KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
+                    commands.push(Command::Quit)
+                }
+                KeyCode::Char(c) => self.input.push(c),
+                KeyCode::Backspace => {
+                    self.input.pop();
+                }
+                KeyCode::Esc if self.input.is_empty() && !debugger.is_paused() => {
+                    commands.push(Command::Pause)
+                }
+                KeyCode::Esc => self.input.clear(),
+                KeyCode::Enter => {
+                    let input = std::mem::take(&mut self.input);
+                    self.message.clear();
+                    if input.trim().is_empty() {
+                        commands.extend(self.last_command);
+                        continue;
+                    }
+                    match input.parse() {
+                        Ok(command) => {
+                            self.last_command = Some(command);
+                            commands.push(command);
+                        }
+                        Err(err) => self.message = err,
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        commands
+    }
+
+    /// The terminal is cleared so everything gets drawn again
+    fn resize(&mut self, columns: u16, rows: u16) {
+        self.size = (columns, rows);
+        self.stdout.queue(Clear(ClearType::All)).unwrap();
+        self.diff.invalidate();
+    }
+
+    /// Every line of the dashboard, padded to the same width
+    fn layout(&self, emulator: &Chip8, debugger: &Debugger) -> Vec<String> {
+        let screen = self.render.lines(&self.pbuf);
+        let screen_width = screen.first().map_or(0, |line| line.chars().count());
+
+        let disassembly: Vec<String> = disassembly_around(emulator, 4, screen.len() - 4)
+            .into_iter()
+            .map(|(address, text)| {
+                let breakpoint = if debugger.breakpoints().contains(&address) {
+                    '*'
+                } else {
+                    ' '
+                };
+                let pc = if address == emulator.pc() { '>' } else { ' ' };
+                format!("{}{} {:03X}  {}", breakpoint, pc, address, text)
+            })
+            .collect();
+
+        let mut lines = side_by_side(
+            boxed("Screen", &screen, screen_width + 2),
+            boxed("Disassembly", &disassembly, DISASSEMBLY_WIDTH),
+        );
+
+        let registers: Vec<String> = format!("{:?}", emulator)
+            .lines()
+            .map(String::from)
+            .collect();
+        let start = debugger.memory_view(emulator);
+        let memory: Vec<String> =
+            hex_view(emulator.memory(), start, registers.len() - 2, HEX_BYTES)
+                .iter()
+                .map(|line| format!(" {}", line))
+                .collect();
+        lines.extend(side_by_side(
+            registers,
+            boxed("Memory", &memory, 6 + HEX_BYTES * 3 + 2),
+        ));
+
+        let state = match debugger.stopped() {
+            Some(stop) => format!("paused, {}", stop),
+            None => "running".to_owned(),
+        };
+        let breakpoints: Vec<String> = debugger
+            .breakpoints()
+            .iter()
+            .map(|address| format!("{:03X}", address))
+            .collect();
+        lines.push(format!(
+            "[{}] breakpoints: {} {}",
+            state,
+            breakpoints.join(" "),
+            self.message
+        ));
+        lines.push(format!("> {}_", self.input));
+
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        lines.iter().map(|line| pad(line, width)).collect()
+    }
+
+    fn present(&mut self, emulator: &Chip8, debugger: &Debugger) {
+        let lines = self.layout(emulator, debugger);
+        let columns = lines.first().map_or(0, |line| line.chars().count());
+        if (self.size.0 as usize) < columns || (self.size.1 as usize) < lines.len() {
+            self.stdout
+                .queue(cursor::MoveTo(0, 0))
+                .unwrap()
+                .queue(Clear(ClearType::CurrentLine))
+                .unwrap();
+            write!(
+                self.stdout,
+                "terminal too small, needs {}x{}",
+                columns,
+                lines.len()
+            )
+            .unwrap();
+            self.stdout.flush().unwrap();
+            self.diff.invalidate();
+            return;
+        }
+
+        for change in self.diff.changes(&lines) {
+            self.stdout
+                .queue(cursor::MoveTo(change.column as u16, change.row as u16))
+                .unwrap();
+            write!(self.stdout, "{}", change.text).unwrap();
+        }
+        self.stdout.flush().unwrap();
+    }
+}
+
+impl Engine for TuiEngine {
+    fn start_loop(&mut self, emulator: &mut Chip8, scheduler: &Scheduler) {
+        let exit = Arc::new(atomic::AtomicBool::new(false));
+        let r = exit.clone();
+
+        ctrlc::set_handler(move || {
+            r.store(true, atomic::Ordering::SeqCst);
+        })
+        .unwrap();
+
+        let guard = TerminalGuard::enter().unwrap();
+        let (columns, rows) = terminal::size().unwrap();
+        self.resize(columns, rows);
+
+        let mut debugger = Debugger::new();
+        let mut pacer = scheduler.pacer();
+        'outer: while !exit.load(atomic::Ordering::SeqCst) {
+            for command in self.handle_events(&debugger) {
+                if !debugger.execute(command, emulator, self, scheduler) {
+                    break 'outer;
+                }
+            }
+
+            for _ in 0..pacer.wait() {
+                debugger.run_frame(emulator, self, scheduler);
+            }
+            self.present(emulator, &debugger);
+        }
+
+        drop(guard);
+    }
+
+    fn draw_sprite(&mut self, x: u8, y: u8, height: u8, sprite: &[u8]) -> bool {
+        // shown by `present` at the end of the frame
+        self.pbuf.draw_sprite(x, y, height, sprite)
+    }
+
+    fn clear_screen(&mut self) {
+        self.pbuf.clear();
+    }
+
+    fn framebuffer(&self) -> &PixelBuf {
+        &self.pbuf
+    }
+}
+
+/// `line` followed by spaces up to `width` characters
+fn pad(line: &str, width: usize) -> String {
+    let len = line.chars().count();
+    format!("{}{}", line, " ".repeat(width.saturating_sub(len)))
+}
+
+/// Draws an ASCII border like comfy-table around `lines`, `width` includes the border
+fn boxed(title: &str, lines: &[String], width: usize) -> Vec<String> {
+    let inner = width - 2;
+    let title = format!("+- {} ", title);
+    let mut boxed = vec![format!("{}{}+", title, "-".repeat(inner + 1 - title.len()))];
+    for line in lines {
+        let line: String = line.chars().take(inner).collect();
+        boxed.push(format!("|{}|", pad(&line, inner)));
+    }
+    boxed.push(format!("+{}+", "-".repeat(inner)));
+
+    boxed
+}
+
+/// Joins two columns of lines with a space between them
+fn side_by_side(left: Vec<String>, right: Vec<String>) -> Vec<String> {
+    let width = left
+        .iter()
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    (0..left.len().max(right.len()))
+        .map(|i| {
+            let left = left.get(i).map_or("", String::as_str);
+            let right = right.get(i).map_or("", String::as_str);
+            format!("{} {}", pad(left, width), right)
+        })
+        .collect()
+}
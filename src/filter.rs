@@ -0,0 +1,83 @@
+use std::str::FromStr;
+
+use crate::{engines::PixelBuf, Palette};
+
+/// How much of its brightness a pixel keeps every frame after it is turned off
+pub const PHOSPHOR_DECAY: f32 = 0.6;
+
+/// Below this a decaying pixel is drawn as background
+const PHOSPHOR_CUTOFF: f32 = 0.05;
+
+/// How the framebuffer reaches the screen
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DisplayFilter {
+    /// Present the framebuffer as it is
+    #[default]
+    None,
+    /// Let turned off pixels fade out like CRT phosphor,
+    /// which hides most XOR flicker
+    Phosphor,
+}
+
+impl FromStr for DisplayFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(DisplayFilter::None),
+            "phosphor" => Ok(DisplayFilter::Phosphor),
+            _ => Err("unknown display filter, expected none or phosphor".to_owned()),
+        }
+    }
+}
+
+/// Brightness of every pixel, updated once per emulated frame
+pub struct Phosphor {
+    intensity: Vec<f32>,
+    decay: f32,
+}
+
+impl Phosphor {
+    pub fn new(decay: f32) -> Self {
+        Phosphor {
+            intensity: vec![0.0; PixelBuf::WIDTH * PixelBuf::HEIGHT],
+            decay: decay.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Lit pixels are at full brightness, the others fade by `decay`
+    pub fn update(&mut self, framebuffer: &PixelBuf) {
+        for y in 0..PixelBuf::HEIGHT {
+            for x in 0..PixelBuf::WIDTH {
+                let intensity = &mut self.intensity[y * PixelBuf::WIDTH + x];
+                *intensity = if framebuffer.pixel(x, y) {
+                    1.0
+                } else if *intensity * self.decay < PHOSPHOR_CUTOFF {
+                    0.0
+                } else {
+                    *intensity * self.decay
+                };
+            }
+        }
+    }
+
+    pub fn intensity(&self, x: usize, y: usize) -> f32 {
+        self.intensity[y * PixelBuf::WIDTH + x]
+    }
+
+    /// `0xRRGGBB` between the palette background and foreground
+    pub fn color(&self, x: usize, y: usize, palette: &Palette) -> u32 {
+        let intensity = self.intensity(x, y);
+        let [_, br, bg, bb] = palette.background.to_be_bytes();
+        let [_, fr, fg, fb] = palette.foreground.to_be_bytes();
+        let blend = |b: u8, f: u8| (b as f32 + (f as f32 - b as f32) * intensity).round() as u8;
+
+        u32::from_be_bytes([0, blend(br, fr), blend(bg, fg), blend(bb, fb)])
+    }
+}
+
+impl Default for Phosphor {
+    fn default() -> Self {
+        Phosphor::new(PHOSPHOR_DECAY)
+    }
+}
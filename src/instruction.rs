@@ -0,0 +1,290 @@
+use std::fmt;
+
+use crate::{word_to_nibbles, MergeNibbles};
+
+/// A decoded CHIP-8 instruction.
+///
+/// `x` and `y` are register indices, `n`/`nn`/`nnn` are the 4, 8 and 12 bit
+/// immediates from the opcode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Instruction {
+    /// 0NNN
+    Sys { nnn: u16 },
+    /// 00E0
+    Cls,
+    /// 00EE
+    Ret,
+    /// 1NNN
+    Jump { nnn: u16 },
+    /// 2NNN
+    Call { nnn: u16 },
+    /// 3XNN
+    SkipEqImm { x: u8, nn: u8 },
+    /// 4XNN
+    SkipNeImm { x: u8, nn: u8 },
+    /// 5XY0
+    SkipEqReg { x: u8, y: u8 },
+    /// 6XNN
+    LoadImm { x: u8, nn: u8 },
+    /// 7XNN
+    AddImm { x: u8, nn: u8 },
+    /// 8XY0
+    Load { x: u8, y: u8 },
+    /// 8XY1
+    Or { x: u8, y: u8 },
+    /// 8XY2
+    And { x: u8, y: u8 },
+    /// 8XY3
+    Xor { x: u8, y: u8 },
+    /// 8XY4
+    Add { x: u8, y: u8 },
+    /// 8XY5
+    Sub { x: u8, y: u8 },
+    /// 8XY6
+    Shr { x: u8, y: u8 },
+    /// 8XY7
+    SubN { x: u8, y: u8 },
+    /// 8XYE
+    Shl { x: u8, y: u8 },
+    /// 9XY0
+    SkipNeReg { x: u8, y: u8 },
+    /// ANNN
+    LoadI { nnn: u16 },
+    /// BNNN
+    JumpV0 { nnn: u16 },
+    /// CXNN
+    Rand { x: u8, nn: u8 },
+    /// DXYN
+    Draw { x: u8, y: u8, n: u8 },
+    /// EX9E
+    SkipKey { x: u8 },
+    /// EXA1
+    SkipNotKey { x: u8 },
+    /// FX07
+    LoadDelay { x: u8 },
+    /// FX0A
+    WaitKey { x: u8 },
+    /// FX15
+    SetDelay { x: u8 },
+    /// FX18
+    SetSound { x: u8 },
+    /// FX1E
+    AddI { x: u8 },
+    /// FX29
+    LoadFont { x: u8 },
+    /// FX33
+    Bcd { x: u8 },
+    /// FX55
+    StoreRegs { x: u8 },
+    /// FX65
+    LoadRegs { x: u8 },
+}
+
+impl Instruction {
+    /// Returns `None` if `opcode` is not a valid instruction.
+    pub fn decode(opcode: u16) -> Option<Instruction> {
+        use Instruction::*;
+
+        let instruction = match word_to_nibbles(&opcode.to_be_bytes()) {
+            [0, 0, 0xE, 0] => Cls,
+            [0, 0, 0xE, 0xE] => Ret,
+            [0, nnn @ ..] => Sys {
+                nnn: nnn.merge_nibbles(),
+            },
+            [0x1, nnn @ ..] => Jump {
+                nnn: nnn.merge_nibbles(),
+            },
+            [0x2, nnn @ ..] => Call {
+                nnn: nnn.merge_nibbles(),
+            },
+            [0x3, x, nn @ ..] => SkipEqImm {
+                x,
+                nn: nn.merge_nibbles(),
+            },
+            [0x4, x, nn @ ..] => SkipNeImm {
+                x,
+                nn: nn.merge_nibbles(),
+            },
+            [0x5, x, y, 0] => SkipEqReg { x, y },
+            [0x6, x, nn @ ..] => LoadImm {
+                x,
+                nn: nn.merge_nibbles(),
+            },
+            [0x7, x, nn @ ..] => AddImm {
+                x,
+                nn: nn.merge_nibbles(),
+            },
+            [0x8, x, y, 0] => Load { x, y },
+            [0x8, x, y, 0x1] => Or { x, y },
+            [0x8, x, y, 0x2] => And { x, y },
+            [0x8, x, y, 0x3] => Xor { x, y },
+            [0x8, x, y, 0x4] => Add { x, y },
+            [0x8, x, y, 0x5] => Sub { x, y },
+            [0x8, x, y, 0x6] => Shr { x, y },
+            [0x8, x, y, 0x7] => SubN { x, y },
+            [0x8, x, y, 0xE] => Shl { x, y },
+            [0x9, x, y, 0] => SkipNeReg { x, y },
+            [0xA, nnn @ ..] => LoadI {
+                nnn: nnn.merge_nibbles(),
+            },
+            [0xB, nnn @ ..] => JumpV0 {
+                nnn: nnn.merge_nibbles(),
+            },
+            [0xC, x, nn @ ..] => Rand {
+                x,
+                nn: nn.merge_nibbles(),
+            },
+            [0xD, x, y, n] => Draw { x, y, n },
+            [0xE, x, 0x9, 0xE] => SkipKey { x },
+            [0xE, x, 0xA, 0x1] => SkipNotKey { x },
+            [0xF, x, 0, 0x7] => LoadDelay { x },
+            [0xF, x, 0, 0xA] => WaitKey { x },
+            [0xF, x, 0x1, 0x5] => SetDelay { x },
+            [0xF, x, 0x1, 0x8] => SetSound { x },
+            [0xF, x, 0x1, 0xE] => AddI { x },
+            [0xF, x, 0x2, 0x9] => LoadFont { x },
+            [0xF, x, 0x3, 0x3] => Bcd { x },
+            [0xF, x, 0x5, 0x5] => StoreRegs { x },
+            [0xF, x, 0x6, 0x5] => LoadRegs { x },
+            _ => return None,
+        };
+
+        Some(instruction)
+    }
+
+    /// Opcode pattern this instruction was decoded from, e.g. `"8XY4"`
+    pub fn pattern(&self) -> &'static str {
+        use Instruction::*;
+
+        match self {
+            Sys { .. } => "0NNN",
+            Cls => "00E0",
+            Ret => "00EE",
+            Jump { .. } => "1NNN",
+            Call { .. } => "2NNN",
+            SkipEqImm { .. } => "3XNN",
+            SkipNeImm { .. } => "4XNN",
+            SkipEqReg { .. } => "5XY0",
+            LoadImm { .. } => "6XNN",
+            AddImm { .. } => "7XNN",
+            Load { .. } => "8XY0",
+            Or { .. } => "8XY1",
+            And { .. } => "8XY2",
+            Xor { .. } => "8XY3",
+            Add { .. } => "8XY4",
+            Sub { .. } => "8XY5",
+            Shr { .. } => "8XY6",
+            SubN { .. } => "8XY7",
+            Shl { .. } => "8XYE",
+            SkipNeReg { .. } => "9XY0",
+            LoadI { .. } => "ANNN",
+            JumpV0 { .. } => "BNNN",
+            Rand { .. } => "CXNN",
+            Draw { .. } => "DXYN",
+            SkipKey { .. } => "EX9E",
+            SkipNotKey { .. } => "EXA1",
+            LoadDelay { .. } => "FX07",
+            WaitKey { .. } => "FX0A",
+            SetDelay { .. } => "FX15",
+            SetSound { .. } => "FX18",
+            AddI { .. } => "FX1E",
+            LoadFont { .. } => "FX29",
+            Bcd { .. } => "FX33",
+            StoreRegs { .. } => "FX55",
+            LoadRegs { .. } => "FX65",
+        }
+    }
+
+    pub fn encode(&self) -> u16 {
+        use Instruction::*;
+
+        fn xnn(prefix: u16, x: u8, nn: u8) -> u16 {
+            prefix << 12 | (x as u16) << 8 | nn as u16
+        }
+        fn xyn(prefix: u16, x: u8, y: u8, n: u8) -> u16 {
+            prefix << 12 | (x as u16) << 8 | (y as u16) << 4 | n as u16
+        }
+
+        match *self {
+            Sys { nnn } => nnn,
+            Cls => 0x00E0,
+            Ret => 0x00EE,
+            Jump { nnn } => 0x1000 | nnn,
+            Call { nnn } => 0x2000 | nnn,
+            SkipEqImm { x, nn } => xnn(0x3, x, nn),
+            SkipNeImm { x, nn } => xnn(0x4, x, nn),
+            SkipEqReg { x, y } => xyn(0x5, x, y, 0),
+            LoadImm { x, nn } => xnn(0x6, x, nn),
+            AddImm { x, nn } => xnn(0x7, x, nn),
+            Load { x, y } => xyn(0x8, x, y, 0),
+            Or { x, y } => xyn(0x8, x, y, 0x1),
+            And { x, y } => xyn(0x8, x, y, 0x2),
+            Xor { x, y } => xyn(0x8, x, y, 0x3),
+            Add { x, y } => xyn(0x8, x, y, 0x4),
+            Sub { x, y } => xyn(0x8, x, y, 0x5),
+            Shr { x, y } => xyn(0x8, x, y, 0x6),
+            SubN { x, y } => xyn(0x8, x, y, 0x7),
+            Shl { x, y } => xyn(0x8, x, y, 0xE),
+            SkipNeReg { x, y } => xyn(0x9, x, y, 0),
+            LoadI { nnn } => 0xA000 | nnn,
+            JumpV0 { nnn } => 0xB000 | nnn,
+            Rand { x, nn } => xnn(0xC, x, nn),
+            Draw { x, y, n } => xyn(0xD, x, y, n),
+            SkipKey { x } => xnn(0xE, x, 0x9E),
+            SkipNotKey { x } => xnn(0xE, x, 0xA1),
+            LoadDelay { x } => xnn(0xF, x, 0x07),
+            WaitKey { x } => xnn(0xF, x, 0x0A),
+            SetDelay { x } => xnn(0xF, x, 0x15),
+            SetSound { x } => xnn(0xF, x, 0x18),
+            AddI { x } => xnn(0xF, x, 0x1E),
+            LoadFont { x } => xnn(0xF, x, 0x29),
+            Bcd { x } => xnn(0xF, x, 0x33),
+            StoreRegs { x } => xnn(0xF, x, 0x55),
+            LoadRegs { x } => xnn(0xF, x, 0x65),
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Instruction::*;
+
+        match *self {
+            Sys { nnn } => write!(f, "call {:03X}", nnn),
+            Cls => write!(f, "disp_clear"),
+            Ret => write!(f, "return;"),
+            Jump { nnn } => write!(f, "goto {:03X};", nnn),
+            Call { nnn } => write!(f, "call {:03X};", nnn),
+            SkipEqImm { x, nn } => write!(f, "if(V{:1X}=={:02X}) skip_next;", x, nn),
+            SkipNeImm { x, nn } => write!(f, "if(V{:1X}!={:02X}) skip_next;", x, nn),
+            SkipEqReg { x, y } => write!(f, "if(V{:1X}==V{:1X}) skip_next;", x, y),
+            LoadImm { x, nn } => write!(f, "V{:1X}={:02X}", x, nn),
+            AddImm { x, nn } => write!(f, "V{:1X}+={:02X}", x, nn),
+            Load { x, y } => write!(f, "V{:1X}=V{:1X}", x, y),
+            Or { x, y } => write!(f, "V{:1X}=V{:1X}|V{:1X}", x, x, y),
+            And { x, y } => write!(f, "V{:1X}=V{:1X}&V{:1X}", x, x, y),
+            Xor { x, y } => write!(f, "V{:1X}=V{:1X}^V{:1X}", x, x, y),
+            Add { x, y } => write!(f, "V{:1X}+=V{:1X}", x, y),
+            Sub { x, y } => write!(f, "V{:1X}-=V{:1X}", x, y),
+            Shr { x, y } => write!(f, "V{:1X}=V{:1X}>>1", x, y),
+            SubN { x, y } => write!(f, "V{:1X}=V{:1X}-V{:1X}", x, y, x),
+            Shl { x, y } => write!(f, "V{:1X}=V{:1X}<<1", x, y),
+            SkipNeReg { x, y } => write!(f, "if(V{:1X}!=V{:1X}) skip_next;", x, y),
+            LoadI { nnn } => write!(f, "I=0x{:03X}", nnn),
+            JumpV0 { nnn } => write!(f, "PC=V0+0x{:03X}", nnn),
+            Rand { x, nn } => write!(f, "V{:1X}=rand() & 0x{:02X}", x, nn),
+            Draw { x, y, n } => write!(f, "draw(V{:1X}, V{:1X}, 0x{:1X})", x, y, n),
+            SkipKey { x } => write!(f, "if(key()==V{:1X})", x),
+            SkipNotKey { x } => write!(f, "if(key()!=V{:1X})", x),
+            LoadDelay { x } => write!(f, "V{:1X}=get_delay()", x),
+            WaitKey { x } => write!(f, "V{:1X}=get_key()", x),
+            SetDelay { x } => write!(f, "delay_timer(V{:1X})", x),
+            SetSound { x } => write!(f, "sound_timer(V{:1X})", x),
+            AddI { x } => write!(f, "I += V{:1X}", x),
+            LoadFont { x } => write!(f, "I = sprite_addr(V{:1X})", x),
+            Bcd { x } => write!(f, "set_BCD(V{:1X})", x),
+            StoreRegs { x } => write!(f, "reg_dump(V{:1X}, &I)", x),
+            LoadRegs { x } => write!(f, "reg_load(V{:1X}, &I)", x),
+        }
+    }
+}
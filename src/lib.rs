@@ -1,8 +1,43 @@
+mod benchmark;
+pub use benchmark::{run_benchmark, BenchmarkReport};
+mod control;
+pub use control::{EmulationControl, SPEEDS};
+mod debugger;
+pub use debugger::{
+    disassembly_around, hex_view, parse_address, Command, Debugger, Location, Stop,
+};
 mod disassembler;
-pub use disassembler::disassemble_file;
+pub use disassembler::{disassemble_at, disassemble_file};
 mod chip8;
 pub mod engines;
-pub use chip8::Chip8;
+pub use chip8::{Chip8, CpuState, Quircks, RECENT_SPRITES};
+mod filter;
+pub use filter::{DisplayFilter, Phosphor, PHOSPHOR_DECAY};
+mod font;
+pub use font::{Canvas, CHAR_HEIGHT, CHAR_WIDTH};
+mod instruction;
+pub use instruction::Instruction;
+mod palette;
+pub use palette::{Palette, PALETTES};
+mod profile;
+pub use profile::{Profile, ProfileReport};
+mod recompiler;
+pub use recompiler::Recompiler;
+mod recorder;
+pub use recorder::Recorder;
+mod screenshot;
+pub use screenshot::{save_png, write_png};
+mod scheduler;
+pub use scheduler::{Clock, FramePacer, FrameState, Scheduler, SystemClock, Timing};
+mod memory_view;
+pub use memory_view::{MemoryView, BYTES_PER_COLUMN, COLUMNS};
+mod movie;
+pub use movie::Movie;
+mod metadata;
+pub use metadata::{QuirksMetadata, RomMetadata};
+mod rng;
+pub use rng::{Rng, Seed, XorShiftRng};
+mod vip;
 
 const FONTSET: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -33,39 +68,20 @@ fn word_to_nibbles([hi, lo]: &[u8; 2]) -> [u8; 4] {
 }
 
 pub trait Engine {
-    fn start_loop(&mut self, emulator: &mut Chip8);
+    fn start_loop(&mut self, emulator: &mut Chip8, scheduler: &Scheduler);
 
     fn draw_sprite(&mut self, x: u8, y: u8, height: u8, sprite: &[u8]) -> bool;
 
-    fn rand(&mut self) -> u8;
-
     fn clear_screen(&mut self);
-}
 
-struct SimpleRng {
-    x: u8,
-    y: u8,
-    z: u8,
-    a: u8,
+    fn framebuffer(&self) -> &engines::PixelBuf;
 }
 
-impl SimpleRng {
-    const fn new() -> Self {
-        SimpleRng {
-            x: 0,
-            y: 0,
-            z: 0,
-            a: 1,
-        }
-    }
-    fn next(&mut self) -> u8 {
-        let t = self.x ^ (self.x << 4);
-        self.x = self.y;
-        self.y = self.z;
-        self.z = self.a;
-        self.a = self.z ^ t ^ (self.z >> 1) ^ (t << 1);
-        self.a
-    }
+/// 64-bit FNV-1a, unlike `DefaultHasher` it is the same on every build
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
 }
 
 pub(crate) trait MergeNibbles {
@@ -85,6 +101,6 @@ impl MergeNibbles for [u8; 2] {
     type Output = u8;
 
     fn merge_nibbles(&self) -> Self::Output {
-        ((self[0] as u8) << 4) | (self[1] as u8)
+        (self[0] << 4) | self[1]
     }
 }
@@ -1,15 +1,23 @@
 use argh::FromArgValue;
-use chip_8::{disassemble_file, engines, Engine};
+use chip_8::{
+    disassemble_file,
+    engines::{self, ColorDepth, ScaleMode, Scaler, TermRender},
+    parse_address, run_benchmark, save_png, Chip8, DisplayFilter, Engine, MemoryView, Movie,
+    Palette, Quircks, Recorder, RomMetadata, Scheduler, Seed, Timing,
+};
 use std::{
-    io::{stdout, BufReader},
+    io::{stdout, BufReader, BufWriter},
     path::PathBuf,
 };
 
+/// Size of a bitmap pixel in --memory-png images
+const MEMORY_PNG_SCALE: usize = 2;
+
 #[derive(argh::FromArgs)]
 /// Simple chip8 emulator
 struct Args {
     #[argh(option, default = "Mode::Minifb")]
-    /// you can choose engine
+    /// minifb, cli or tui (terminal debugger)
     mode: Mode,
 
     #[argh(option, default = "20")]
@@ -20,6 +28,114 @@ struct Args {
     /// show pseudo-assembly instead of emulation
     disassemble: bool,
 
+    #[argh(switch)]
+    /// run headless without sleeping and report emulation speed
+    benchmark: bool,
+
+    #[argh(option, default = "1_000_000")]
+    /// number of instructions to run with --benchmark
+    cycles: u64,
+
+    #[argh(switch)]
+    /// run --benchmark through the block recompiler (fixed timing only)
+    recompile: bool,
+
+    #[argh(option)]
+    /// instructions per second, 600 unless set in roms.json
+    cpu_hz: Option<u32>,
+
+    #[argh(option)]
+    /// instructions per frame, overrides --cpu-hz
+    ipf: Option<u32>,
+
+    #[argh(option)]
+    /// delay and sound timer rate, 60 unless set in roms.json
+    timer_hz: Option<u32>,
+
+    #[argh(option, default = "Seed::default()")]
+    /// seed for CXNN random numbers, or entropy to draw one from the OS and print it
+    seed: Seed,
+
+    #[argh(option, default = "Timing::Fixed")]
+    /// fixed instructions per frame, or vip to use COSMAC VIP cycle costs
+    timing: Timing,
+
+    #[argh(option)]
+    /// record keypad input to a movie file (minifb only)
+    record_movie: Option<PathBuf>,
+
+    #[argh(option)]
+    /// replay a movie headless and check it ends on the recorded picture
+    play_movie: Option<PathBuf>,
+
+    #[argh(option)]
+    /// run headless for this many frames and save a screenshot
+    screenshot_after: Option<usize>,
+
+    #[argh(option, default = "PathBuf::from(\"screenshot.png\")")]
+    /// where --screenshot-after saves the PNG
+    screenshot_path: PathBuf,
+
+    #[argh(option, default = "10")]
+    /// size of a CHIP-8 pixel in screenshots
+    screenshot_scale: u32,
+
+    #[argh(option)]
+    /// run headless and save memory as 8 pixel wide bitmaps to a PNG
+    memory_png: Option<PathBuf>,
+
+    #[argh(option, default = "0")]
+    /// frames to run before --memory-png, so the ROM can build its sprites
+    memory_frames: usize,
+
+    #[argh(option, from_str_fn(parse_address), default = "0")]
+    /// first address in the --memory-png image, in hex
+    memory_start: u16,
+
+    #[argh(option)]
+    /// classic, green, amber, lcd, octo or 2 to 4 comma separated RRGGBB colors
+    palette: Option<Palette>,
+
+    #[argh(option, default = "DisplayFilter::None")]
+    /// none or phosphor to fade pixels out (minifb)
+    display_filter: DisplayFilter,
+
+    #[argh(option, default = "ScaleMode::Integer")]
+    /// integer for equally sized pixels or aspect to fill the window (minifb)
+    scaling: ScaleMode,
+
+    #[argh(switch)]
+    /// draw pixels without gaps between them (minifb)
+    no_grid: bool,
+
+    #[argh(option, default = "4")]
+    /// width of the gap between pixels (minifb)
+    grid_gap: usize,
+
+    #[argh(switch)]
+    /// darken every other row like a CRT (minifb)
+    scanlines: bool,
+
+    #[argh(option, default = "TermRender::HalfBlock")]
+    /// ascii, half-block (2 pixels per character) or braille (8 pixels per character) (cli)
+    term_render: TermRender,
+
+    #[argh(option, default = "ColorDepth::detect()")]
+    /// auto, none, 16, 256 or truecolor, guessed from the environment by default (cli)
+    term_colors: ColorDepth,
+
+    #[argh(option)]
+    /// count executed instructions and write a coverage and hot spot report on exit
+    profile: Option<PathBuf>,
+
+    #[argh(option)]
+    /// record gameplay to a .gif, a .y4m or a directory of PNGs
+    record: Option<PathBuf>,
+
+    #[argh(option, default = "4")]
+    /// size of a CHIP-8 pixel in recordings
+    record_scale: u32,
+
     #[argh(positional)]
     rom_path: PathBuf,
 }
@@ -27,12 +143,14 @@ struct Args {
 enum Mode {
     Minifb,
     Cli,
+    Tui,
 }
 impl FromArgValue for Mode {
     fn from_arg_value(value: &str) -> Result<Self, String> {
         match value {
             "minifb" => Ok(Mode::Minifb),
             "cli" => Ok(Mode::Cli),
+            "tui" => Ok(Mode::Tui),
             _ => Err("unknown mode".to_owned()),
         }
     }
@@ -48,28 +166,218 @@ fn main() {
         return;
     }
 
+    if args.profile.is_some() && (args.benchmark || args.play_movie.is_some()) {
+        // both run their own emulators, there is nothing to profile
+        eprintln!("--profile cannot be combined with --benchmark or --play-movie");
+        std::process::exit(2);
+    }
+
+    if args.record_movie.is_some() && !matches!(args.mode, Mode::Minifb) {
+        // only the minifb engine records keypad input
+        eprintln!("--record-movie needs --mode minifb");
+        std::process::exit(2);
+    }
+
+    if args.benchmark && args.cycles == 0 {
+        eprintln!("--cycles must be at least 1");
+        std::process::exit(2);
+    }
+
+    if args.recompile && (!args.benchmark || args.timing != Timing::Fixed) {
+        // the recompiler has no cycle costs and no engine drives it yet
+        eprintln!("--recompile needs --benchmark and --timing fixed");
+        std::process::exit(2);
+    }
+
+    let data = std::fs::read(&args.rom_path).unwrap();
+
+    if let Some(path) = &args.play_movie {
+        let file = std::fs::File::open(path).unwrap();
+        let movie = Movie::read(BufReader::new(file)).unwrap();
+
+        match movie.play(&data) {
+            Ok(()) => println!("replay of {} frames matches", movie.frames.len()),
+            Err(err) => {
+                eprintln!("replay failed: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let metadata = RomMetadata::find(&args.rom_path);
+    let scheduler = create_scheduler(&args, metadata.as_ref());
+    let palette = create_palette(&args, metadata.as_ref());
+
+    let mut quircks = Quircks::default();
+    if let Some(metadata) = &metadata {
+        metadata.apply_quircks(&mut quircks);
+    }
+    let seed = args.seed.value();
+    if args.seed == Seed::Entropy {
+        eprintln!("seeded with --seed {:#x}", seed);
+    }
+
+    if args.benchmark {
+        let report = run_benchmark(
+            &data,
+            args.cycles,
+            &scheduler,
+            quircks,
+            Seed::Fixed(seed),
+            args.recompile,
+        );
+        println!("{}", report);
+        return;
+    }
+
+    let mut chip = Chip8::new();
+    chip.set_rng(Seed::Fixed(seed).into_rng());
+    chip.quircks = quircks;
+    chip.load_game(&data);
+    chip.set_profiling(args.profile.is_some());
+
+    if let Some(path) = &args.memory_png {
+        let mut engine = engines::HeadlessEngine::new(args.memory_frames);
+        engine.start_loop(&mut chip, &scheduler);
+
+        let mut view = MemoryView::new(MEMORY_PNG_SCALE);
+        view.jump(args.memory_start);
+        view.save_png(&chip, &palette, path).unwrap();
+    } else if let Some(frames) = args.screenshot_after {
+        let mut engine = engines::HeadlessEngine::new(frames);
+        engine.start_loop(&mut chip, &scheduler);
+
+        save_png(
+            engine.framebuffer(),
+            args.screenshot_scale,
+            &palette,
+            &args.screenshot_path,
+        )
+        .unwrap();
+    } else {
+        start_engine(&args, palette, &mut chip, &scheduler, &data, seed);
+    }
+
+    if let (Some(path), Some(profile)) = (&args.profile, chip.profile()) {
+        let report = profile.report(chip.memory(), data.len());
+        let text = format!("{}\n\n{}", report, report.annotated_disassembly());
+        std::fs::write(path, text).unwrap();
+    }
+}
+
+/// Runs the engine picked with `--mode`
+fn start_engine(
+    args: &Args,
+    palette: Palette,
+    chip: &mut Chip8,
+    scheduler: &Scheduler,
+    data: &[u8],
+    seed: u64,
+) {
+    // an entropy seed was drawn above, so it replays like a fixed one
+    let movie = args
+        .record_movie
+        .as_ref()
+        .map(|_| Movie::new(data, seed, &chip.quircks, scheduler));
+
+    let recorder = args
+        .record
+        .as_ref()
+        .map(|path| Recorder::create(path, args.record_scale, palette).unwrap());
+
     match args.mode {
-        Mode::Minifb => start_minifb_engine(args.scale, args.rom_path),
-        Mode::Cli => start_cli_engine(args.rom_path),
+        Mode::Minifb => {
+            let movie = start_minifb_engine(args, palette, chip, scheduler, movie, recorder);
+            if let (Some(path), Some(movie)) = (&args.record_movie, movie) {
+                let file = std::fs::File::create(path).unwrap();
+                movie.write(BufWriter::new(file)).unwrap();
+            }
+        }
+        Mode::Cli => start_cli_engine(args, palette, chip, scheduler, recorder),
+        Mode::Tui => {
+            let mut engine = engines::TuiEngine::new(stdout());
+            engine.set_render(args.term_render);
+            engine.start_loop(chip, scheduler);
+        }
     }
 }
 
-fn start_minifb_engine(scale: u8, path: PathBuf) {
-    let mut engine = engines::MinifbEngine::create(scale as usize).unwrap();
-    let mut chip = chip_8::Chip8::new();
+/// Command line options take precedence over roms.json
+fn create_scheduler(args: &Args, metadata: Option<&RomMetadata>) -> Scheduler {
+    let mut scheduler = Scheduler {
+        timing: args.timing,
+        ..Default::default()
+    };
+    if let Some(metadata) = metadata {
+        metadata.apply_scheduler(&mut scheduler);
+    }
 
-    let data = std::fs::read(path).unwrap();
-    chip.load_game(&data);
+    if let Some(cpu_hz) = args.cpu_hz {
+        scheduler.cpu_hz = cpu_hz;
+        scheduler.ipf = None;
+    }
+    if let Some(timer_hz) = args.timer_hz {
+        scheduler.timer_hz = timer_hz;
+    }
+    if let Some(ipf) = args.ipf {
+        scheduler.ipf = Some(ipf);
+    }
 
-    engine.start_loop(&mut chip);
+    scheduler
 }
 
-fn start_cli_engine(path: PathBuf) {
-    let mut engine = engines::CliEngine::new(stdout());
-    let mut chip = chip_8::Chip8::new();
+/// Same precedence as the scheduler, `--palette` wins over roms.json
+fn create_palette(args: &Args, metadata: Option<&RomMetadata>) -> Palette {
+    let mut palette = Palette::default();
+    if let Some(metadata) = metadata {
+        metadata.apply_palette(&mut palette);
+    }
 
-    let data = std::fs::read(path).unwrap();
-    chip.load_game(&data);
+    args.palette.unwrap_or(palette)
+}
+
+fn start_minifb_engine(
+    args: &Args,
+    palette: Palette,
+    chip: &mut Chip8,
+    scheduler: &Scheduler,
+    movie: Option<Movie>,
+    recorder: Option<Recorder>,
+) -> Option<Movie> {
+    let mut engine = engines::MinifbEngine::create(args.scale.max(1) as usize).unwrap();
+    engine.set_palette(palette);
+    engine.set_display_filter(args.display_filter);
+    engine.set_scaler(Scaler {
+        mode: args.scaling,
+        gap: if args.no_grid { 0 } else { args.grid_gap },
+        scanlines: args.scanlines,
+    });
+    if let Some(movie) = movie {
+        engine.record_movie(movie);
+    }
+    if let Some(recorder) = recorder {
+        engine.record(recorder);
+    }
+
+    engine.start_loop(chip, scheduler);
+
+    engine.take_movie()
+}
+
+fn start_cli_engine(
+    args: &Args,
+    palette: Palette,
+    chip: &mut Chip8,
+    scheduler: &Scheduler,
+    recorder: Option<Recorder>,
+) {
+    let mut engine = engines::CliEngine::new(stdout());
+    engine.set_render(args.term_render);
+    engine.set_colors(palette, args.term_colors);
+    if let Some(recorder) = recorder {
+        engine.record(recorder);
+    }
 
-    engine.start_loop(&mut chip);
+    engine.start_loop(chip, scheduler);
 }
@@ -0,0 +1,155 @@
+use std::{fs::File, io::BufWriter, io::Write, ops::Range, path::Path};
+
+use crate::{
+    font::{Canvas, CHAR_HEIGHT},
+    screenshot::write_rgb_png,
+    Chip8, Palette,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Bytes in every column, each drawn as a row of 8 pixels
+pub const BYTES_PER_COLUMN: usize = 32;
+/// Columns side by side, so a band of columns covers 0x200 bytes
+pub const COLUMNS: usize = 16;
+const BAND_BYTES: usize = BYTES_PER_COLUMN * COLUMNS;
+
+/// Horizontal distance between columns, wide enough for a 3 digit address
+const COLUMN_PITCH: usize = 14;
+const LABEL_HEIGHT: usize = CHAR_HEIGHT + 1;
+const BAND_HEIGHT: usize = LABEL_HEIGHT + BYTES_PER_COLUMN + 4;
+
+/// Memory drawn as 8 pixel wide bitmaps, which makes sprites easy to spot.
+///
+/// Columns of `BYTES_PER_COLUMN` bytes are labeled with their address.
+/// The bytes `I` points to are drawn on `Palette::plane2` and the sources
+/// of the last DXYN sprites on `Palette::blend`.
+#[derive(Debug, Clone)]
+pub struct MemoryView {
+    start: u16,
+    pub scale: usize,
+}
+
+impl MemoryView {
+    pub fn new(scale: usize) -> Self {
+        MemoryView {
+            start: 0,
+            scale: scale.max(1),
+        }
+    }
+
+    /// First address shown
+    pub fn start(&self) -> u16 {
+        self.start
+    }
+
+    /// Starts the view at the column containing `address`
+    pub fn jump(&mut self, address: u16) {
+        let address = (address as usize).min(0xFFF);
+        self.start = (address - address % BYTES_PER_COLUMN) as u16;
+    }
+
+    /// Moves the view by `columns` columns, negative scrolls back
+    pub fn scroll(&mut self, columns: isize) {
+        let start = self.start as isize + columns * BYTES_PER_COLUMN as isize;
+        self.jump(start.clamp(0, 0xFFF) as u16);
+    }
+
+    /// Size in pixels of the view showing everything from `start()` to the end of memory
+    pub fn size(&self) -> (usize, usize) {
+        let bands = (0x1000 - self.start as usize).div_ceil(BAND_BYTES);
+        (
+            COLUMNS * COLUMN_PITCH * self.scale,
+            bands * BAND_HEIGHT * self.scale,
+        )
+    }
+
+    /// Bytes `I` points to and the sources of recent sprites
+    fn highlights(&self, emulator: &Chip8) -> (Range<usize>, Vec<Range<usize>>) {
+        let i = emulator.cpu_state().i as usize;
+        // as much as the last sprite drawn from I, or a single byte
+        let i_len = emulator
+            .recent_sprites()
+            .find(|&(source, _)| source as usize == i)
+            .map_or(1, |(_, n)| (n as usize).max(1));
+        let sprites = emulator
+            .recent_sprites()
+            .map(|(source, n)| source as usize..source as usize + n as usize)
+            .collect();
+
+        (i..i + i_len, sprites)
+    }
+
+    /// Draws as many bands of columns as fit on the canvas
+    pub(crate) fn render(&self, emulator: &Chip8, palette: &Palette, canvas: &mut Canvas) {
+        let scale = self.scale;
+        canvas.fill_rect(0, 0, canvas.width, canvas.height, palette.background);
+
+        let memory = emulator.memory();
+        let (i, sprites) = self.highlights(emulator);
+        let background = |address: usize| {
+            if i.contains(&address) {
+                palette.plane2
+            } else if sprites.iter().any(|sprite| sprite.contains(&address)) {
+                palette.blend
+            } else {
+                palette.background
+            }
+        };
+        let columns = (self.start as usize..memory.len()).step_by(BYTES_PER_COLUMN);
+        for (n, column) in columns.enumerate() {
+            let x = (n % COLUMNS) * COLUMN_PITCH * scale;
+            let y = (n / COLUMNS) * BAND_HEIGHT * scale;
+            if y >= canvas.height {
+                break;
+            }
+
+            canvas.text(x, y, &format!("{:03X}", column), palette.foreground, scale);
+            for row in 0..BYTES_PER_COLUMN {
+                let address = column + row;
+                let py = y + (LABEL_HEIGHT + row) * scale;
+                canvas.fill_rect(x, py, 8 * scale, scale, background(address));
+                for bit in 0..8 {
+                    if memory[address] & (0x80 >> bit) != 0 {
+                        let px = x + bit * scale;
+                        canvas.fill_rect(px, py, scale, scale, palette.foreground);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Pixels of everything from `start()` to the end of memory, `0xRRGGBB` row by row
+    pub fn image(&self, emulator: &Chip8, palette: &Palette) -> (usize, usize, Vec<u32>) {
+        let (width, height) = self.size();
+        let mut pixels = vec![palette.background; width * height];
+        self.render(
+            emulator,
+            palette,
+            &mut Canvas {
+                buffer: &mut pixels,
+                stride: width,
+                left: 0,
+                width,
+                height,
+            },
+        );
+
+        (width, height, pixels)
+    }
+
+    pub fn write_png(&self, emulator: &Chip8, palette: &Palette, writer: impl Write) -> Result<()> {
+        let (width, height, pixels) = self.image(emulator, palette);
+        write_rgb_png(width, height, &pixels, writer)
+    }
+
+    pub fn save_png(
+        &self,
+        emulator: &Chip8,
+        palette: &Palette,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        let file = File::create(path)?;
+        self.write_png(emulator, palette, BufWriter::new(file))
+    }
+}
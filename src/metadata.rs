@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::{chip8::Quircks, Palette, Scheduler};
+
+/// Name of the file next to the ROMs describing them
+pub const METADATA_FILE: &str = "roms.json";
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RomMetadata {
+    pub title: String,
+    pub file: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub quirks: QuirksMetadata,
+    pub cpu_hz: Option<u32>,
+    pub timer_hz: Option<u32>,
+    pub ipf: Option<u32>,
+    /// Palette name or colors, see `Palette::from_str`
+    pub palette: Option<Palette>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QuirksMetadata {
+    /// FX55 and FX65 leave I unchanged, the opposite of `Quircks::load_store`
+    #[serde(default)]
+    pub load_store: bool,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub jump: bool,
+    #[serde(default)]
+    pub vf_reset: bool,
+}
+
+impl RomMetadata {
+    /// Looks up `rom_path` in the `roms.json` of its directory
+    pub fn find(rom_path: &Path) -> Option<RomMetadata> {
+        let file_name = rom_path.file_name()?.to_str()?;
+        let json = std::fs::read(rom_path.with_file_name(METADATA_FILE)).ok()?;
+        let entries: Vec<RomMetadata> = serde_json::from_slice(&json).ok()?;
+
+        entries.into_iter().find(|entry| entry.file == file_name)
+    }
+
+    pub fn apply_scheduler(&self, scheduler: &mut Scheduler) {
+        if let Some(cpu_hz) = self.cpu_hz {
+            scheduler.cpu_hz = cpu_hz;
+        }
+        if let Some(timer_hz) = self.timer_hz {
+            scheduler.timer_hz = timer_hz;
+        }
+        if let Some(ipf) = self.ipf {
+            scheduler.ipf = Some(ipf);
+        }
+    }
+
+    pub fn apply_palette(&self, palette: &mut Palette) {
+        if let Some(rom_palette) = self.palette {
+            *palette = rom_palette;
+        }
+    }
+
+    pub fn apply_quircks(&self, quircks: &mut Quircks) {
+        quircks.load_store = !self.quirks.load_store;
+        quircks.shift = self.quirks.shift;
+        quircks.jump = self.quirks.jump;
+        quircks.vf_reset = self.quirks.vf_reset;
+    }
+}
@@ -0,0 +1,180 @@
+use std::io::{BufRead, Write};
+
+use crate::{
+    engines::{HeadlessEngine, PixelBuf},
+    fnv1a, Chip8, Engine, FrameState, Quircks, Scheduler, Seed, Timing,
+};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+const HEADER: &str = "chip8-movie 2";
+
+/// Everything needed to replay a session: the settings it was started with
+/// and the keypad state of every emulated frame.
+///
+/// Stored as text:
+/// ```text
+/// chip8-movie 2
+/// rom <hash>
+/// seed <seed>
+/// quirks <enabled quirks, see `Quircks`>
+/// timing <fixed|vip>
+/// cpu_hz <instructions per second>
+/// timer_hz <timer ticks per second>
+/// ipf <instructions per frame or none>
+/// hash <framebuffer hash after the last frame>
+/// frames
+/// <keypad mask of frame 0>
+/// ...
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub rom_hash: u64,
+    pub seed: u64,
+    pub quircks: Quircks,
+    pub timing: Timing,
+    pub cpu_hz: u32,
+    pub timer_hz: u32,
+    pub ipf: Option<u32>,
+    /// Keypad state of each frame, bit N is key N
+    pub frames: Vec<u16>,
+    pub framebuffer_hash: u64,
+}
+
+impl Movie {
+    /// Starts an empty recording of `rom` running with the given settings
+    pub fn new(rom: &[u8], seed: u64, quircks: &Quircks, scheduler: &Scheduler) -> Self {
+        Movie {
+            rom_hash: fnv1a(rom),
+            seed,
+            quircks: *quircks,
+            timing: scheduler.timing,
+            cpu_hz: scheduler.cpu_hz,
+            timer_hz: scheduler.timer_hz,
+            ipf: scheduler.ipf,
+            frames: Vec::new(),
+            framebuffer_hash: 0,
+        }
+    }
+
+    /// Call before every emulated frame
+    pub fn record_frame(&mut self, emulator: &Chip8) {
+        self.frames.push(emulator.keypad());
+    }
+
+    /// Call after the last frame
+    pub fn finish(&mut self, framebuffer: &PixelBuf) {
+        self.framebuffer_hash = framebuffer.hash();
+    }
+
+    pub fn scheduler(&self) -> Scheduler {
+        Scheduler {
+            cpu_hz: self.cpu_hz,
+            timer_hz: self.timer_hz,
+            ipf: self.ipf,
+            timing: self.timing,
+        }
+    }
+
+    /// Replays the movie headless, failing if it ends on a different picture
+    pub fn play(&self, rom: &[u8]) -> Result<()> {
+        if fnv1a(rom) != self.rom_hash {
+            return Err("the movie was recorded with a different ROM".into());
+        }
+
+        let mut emulator = Chip8::new();
+        emulator.set_rng(Seed::Fixed(self.seed).into_rng());
+        emulator.quircks = self.quircks;
+        emulator.load_game(rom);
+
+        let mut engine = HeadlessEngine::new(0);
+        let scheduler = self.scheduler();
+        let mut state = FrameState::default();
+        for &keypad in &self.frames {
+            emulator.set_keypad(keypad);
+            scheduler.run_frame(&mut state, &mut emulator, &mut engine);
+        }
+
+        let hash = engine.framebuffer().hash();
+        if hash != self.framebuffer_hash {
+            return Err(format!(
+                "framebuffer hash {:016x} does not match the recorded {:016x}",
+                hash, self.framebuffer_hash
+            )
+            .into());
+        }
+
+        Ok(())
+    }
+
+    pub fn write(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writeln!(writer, "{}", HEADER)?;
+        writeln!(writer, "rom {:016x}", self.rom_hash)?;
+        writeln!(writer, "seed {}", self.seed)?;
+        writeln!(writer, "quirks {}", self.quircks)?;
+        let timing = match self.timing {
+            Timing::Fixed => "fixed",
+            Timing::Vip => "vip",
+        };
+        writeln!(writer, "timing {}", timing)?;
+        writeln!(writer, "cpu_hz {}", self.cpu_hz)?;
+        writeln!(writer, "timer_hz {}", self.timer_hz)?;
+        match self.ipf {
+            Some(ipf) => writeln!(writer, "ipf {}", ipf)?,
+            None => writeln!(writer, "ipf none")?,
+        }
+        writeln!(writer, "hash {:016x}", self.framebuffer_hash)?;
+        writeln!(writer, "frames")?;
+        for keypad in &self.frames {
+            writeln!(writer, "{:04x}", keypad)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(reader: impl BufRead) -> Result<Movie> {
+        let mut lines = reader.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err("not a chip8 movie".into());
+        }
+
+        let mut field = |name: &str| -> Result<String> {
+            let line = lines.next().transpose()?.unwrap_or_default();
+            match line.split_once(' ') {
+                Some((key, value)) if key == name => Ok(value.to_owned()),
+                _ => Err(format!("expected {}, found {:?}", name, line).into()),
+            }
+        };
+
+        let rom_hash = u64::from_str_radix(&field("rom")?, 16)?;
+        let seed = field("seed")?.parse()?;
+        let quircks = field("quirks")?.parse()?;
+        let timing = field("timing")?.parse()?;
+        let cpu_hz = field("cpu_hz")?.parse()?;
+        let timer_hz = field("timer_hz")?.parse()?;
+        let ipf = match field("ipf")?.as_str() {
+            "none" => None,
+            ipf => Some(ipf.parse()?),
+        };
+        let framebuffer_hash = u64::from_str_radix(&field("hash")?, 16)?;
+
+        if lines.next().transpose()?.as_deref() != Some("frames") {
+            return Err("expected frames".into());
+        }
+        let frames = lines
+            .map(|line| Ok(u16::from_str_radix(line?.trim(), 16)?))
+            .collect::<Result<_>>()?;
+
+        Ok(Movie {
+            rom_hash,
+            seed,
+            quircks,
+            timing,
+            cpu_hz,
+            timer_hz,
+            ipf,
+            frames,
+            framebuffer_hash,
+        })
+    }
+}
@@ -0,0 +1,160 @@
+use std::{fmt, str::FromStr};
+
+use serde::Deserialize;
+
+/// Named palettes accepted by `--palette` and `roms.json`
+pub const PALETTES: [(&str, Palette); 5] = [
+    ("classic", Palette::CLASSIC),
+    ("green", Palette::GREEN),
+    ("amber", Palette::AMBER),
+    ("lcd", Palette::LCD),
+    ("octo", Palette::OCTO),
+];
+
+/// Colors as `0xRRGGBB`, one for every combination of the two bit planes
+/// XO-CHIP draws to. Plain CHIP-8 only uses `background` and `foreground`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Palette {
+    /// No plane set
+    pub background: u32,
+    /// First plane set
+    pub foreground: u32,
+    /// Second plane set
+    pub plane2: u32,
+    /// Both planes set
+    pub blend: u32,
+}
+
+impl Palette {
+    pub const CLASSIC: Palette = Palette {
+        background: 0x00_00_00,
+        foreground: 0xFF_FF_FF,
+        plane2: 0xAA_AA_AA,
+        blend: 0x55_55_55,
+    };
+
+    pub const GREEN: Palette = Palette {
+        background: 0x0A_14_0A,
+        foreground: 0x33_FF_66,
+        plane2: 0x1A_99_40,
+        blend: 0x0F_4D_1F,
+    };
+
+    pub const AMBER: Palette = Palette {
+        background: 0x1A_0F_00,
+        foreground: 0xFF_B0_00,
+        plane2: 0xCC_7A_00,
+        blend: 0x66_3D_00,
+    };
+
+    /// Four shades of the original Game Boy screen
+    pub const LCD: Palette = Palette {
+        background: 0x9B_BC_0F,
+        foreground: 0x0F_38_0F,
+        plane2: 0x30_62_30,
+        blend: 0x8B_AC_0F,
+    };
+
+    /// Defaults of the Octo IDE
+    pub const OCTO: Palette = Palette {
+        background: 0x99_66_00,
+        foreground: 0xFF_CC_00,
+        plane2: 0xFF_66_00,
+        blend: 0x66_22_00,
+    };
+
+    /// Color of a pixel, bit 0 of `planes` is the first plane and bit 1 the second
+    pub fn color(&self, planes: u8) -> u32 {
+        match planes & 0b11 {
+            0 => self.background,
+            1 => self.foreground,
+            2 => self.plane2,
+            _ => self.blend,
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::CLASSIC
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// A palette name or 2 to 4 comma separated hex colors,
+    /// e.g. `amber` or `#000000,#33ff66`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((_, palette)) = PALETTES.iter().find(|(name, _)| *name == s) {
+            return Ok(*palette);
+        }
+
+        let colors = s
+            .split(',')
+            .map(|color| {
+                let hex = color.trim().trim_start_matches('#');
+                match u32::from_str_radix(hex, 16) {
+                    Ok(color) if hex.len() == 6 => Ok(color),
+                    _ => Err(format!("invalid color {:?}, expected RRGGBB", color)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        match colors[..] {
+            [background, foreground] => Ok(Palette {
+                background,
+                foreground,
+                plane2: mix(background, foreground),
+                blend: mix(background, foreground),
+            }),
+            [background, foreground, plane2] => Ok(Palette {
+                background,
+                foreground,
+                plane2,
+                blend: mix(foreground, plane2),
+            }),
+            [background, foreground, plane2, blend] => Ok(Palette {
+                background,
+                foreground,
+                plane2,
+                blend,
+            }),
+            _ => {
+                let names: Vec<_> = PALETTES.iter().map(|(name, _)| *name).collect();
+                Err(format!(
+                    "expected one of {} or 2 to 4 comma separated colors",
+                    names.join(", ")
+                ))
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for Palette {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{:06x},#{:06x},#{:06x},#{:06x}",
+            self.background, self.foreground, self.plane2, self.blend
+        )
+    }
+}
+
+/// Average of two colors, channel by channel
+fn mix(a: u32, b: u32) -> u32 {
+    let [_, ar, ag, ab] = a.to_be_bytes();
+    let [_, br, bg, bb] = b.to_be_bytes();
+    let avg = |a: u8, b: u8| ((a as u32 + b as u32) / 2) as u8;
+
+    u32::from_be_bytes([0, avg(ar, br), avg(ag, bg), avg(ab, bb)])
+}
@@ -0,0 +1,281 @@
+use std::{collections::BTreeMap, fmt, ops::RangeInclusive};
+
+use comfy_table::Table;
+
+use crate::{disassemble_at, Instruction};
+
+/// Rows of the hot address table in `ProfileReport`
+const HOT_ADDRESSES: usize = 20;
+
+/// Execution counts collected by `Chip8` while profiling is enabled,
+/// see [`Chip8::set_profiling`](crate::Chip8::set_profiling)
+#[derive(Debug, Clone)]
+pub struct Profile {
+    /// Times an instruction was fetched from each address
+    executed: Box<[u64; 4096]>,
+    /// Times each address was the target of a 2NNN
+    calls: Box<[u64; 4096]>,
+    /// Executed instructions by opcode pattern
+    opcodes: BTreeMap<&'static str, u64>,
+}
+
+impl Profile {
+    pub fn new() -> Self {
+        Profile {
+            executed: Box::new([0; 4096]),
+            calls: Box::new([0; 4096]),
+            opcodes: BTreeMap::new(),
+        }
+    }
+
+    pub(crate) fn record(&mut self, address: u16, instruction: Instruction) {
+        self.executed[address as usize & 0xFFF] += 1;
+        if let Instruction::Call { nnn } = instruction {
+            self.calls[nnn as usize & 0xFFF] += 1;
+        }
+        *self.opcodes.entry(instruction.pattern()).or_default() += 1;
+    }
+
+    /// Times the instruction at `address` was run
+    pub fn executed(&self, address: u16) -> u64 {
+        self.executed[address as usize & 0xFFF]
+    }
+
+    /// Times the subroutine at `address` was called
+    pub fn calls(&self, address: u16) -> u64 {
+        self.calls[address as usize & 0xFFF]
+    }
+
+    /// Instructions run while profiling
+    pub fn total(&self) -> u64 {
+        self.executed.iter().sum()
+    }
+
+    /// Opcode patterns such as `"DXYN"` with how often they ran, most frequent first
+    pub fn opcodes(&self) -> Vec<(&'static str, u64)> {
+        let mut opcodes: Vec<_> = self.opcodes.iter().map(|(&p, &n)| (p, n)).collect();
+        opcodes.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        opcodes
+    }
+
+    /// Executed addresses with their counts, most frequent first
+    pub fn hot_addresses(&self) -> Vec<(u16, u64)> {
+        Self::sorted(&self.executed)
+    }
+
+    /// Called subroutines with their call counts, most called first
+    pub fn subroutines(&self) -> Vec<(u16, u64)> {
+        Self::sorted(&self.calls)
+    }
+
+    fn sorted(counts: &[u64; 4096]) -> Vec<(u16, u64)> {
+        let mut sorted: Vec<_> = (0..4096)
+            .filter(|&address| counts[address] > 0)
+            .map(|address| (address as u16, counts[address]))
+            .collect();
+        // stable, so equal counts stay in address order
+        sorted.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        sorted
+    }
+
+    /// Report for a ROM of `rom_len` bytes loaded at 0x200 into `memory`
+    pub fn report<'a>(&'a self, memory: &'a [u8; 4096], rom_len: usize) -> ProfileReport<'a> {
+        ProfileReport::new(self, memory, rom_len)
+    }
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Coverage and hot spots of a profiled session.
+///
+/// `Display` prints tables of the hottest addresses, opcodes, subroutines
+/// and never executed code, `annotated_disassembly` the whole ROM with
+/// execution counts.
+///
+/// Code is told apart from data by following jumps, calls and skips from
+/// 0x200 and from every executed address. Targets of BNNN are only known
+/// when they ran, so code only reached through them is taken for data.
+pub struct ProfileReport<'a> {
+    profile: &'a Profile,
+    memory: &'a [u8; 4096],
+    rom: RangeInclusive<usize>,
+    /// Addresses where an instruction starts
+    code: Box<[bool; 4096]>,
+}
+
+impl<'a> ProfileReport<'a> {
+    fn new(profile: &'a Profile, memory: &'a [u8; 4096], rom_len: usize) -> Self {
+        let rom = 0x200..=(0x200 + rom_len).clamp(0x201, 4096) - 1;
+        let mut code = Box::new([false; 4096]);
+
+        let mut pending: Vec<usize> = (0..4096)
+            .filter(|&address| profile.executed[address] > 0)
+            .collect();
+        pending.push(0x200);
+        while let Some(address) = pending.pop() {
+            if address >= 4095 || code[address] {
+                continue;
+            }
+            let opcode = u16::from_be_bytes([memory[address], memory[address + 1]]);
+            let Some(instruction) = Instruction::decode(opcode) else {
+                continue;
+            };
+            code[address] = true;
+
+            use Instruction::*;
+            let next = address + 2;
+            match instruction {
+                Sys { .. } | Ret | JumpV0 { .. } => {}
+                Jump { nnn } => pending.push(nnn as usize),
+                Call { nnn } => pending.extend([nnn as usize, next]),
+                SkipEqImm { .. }
+                | SkipNeImm { .. }
+                | SkipEqReg { .. }
+                | SkipNeReg { .. }
+                | SkipKey { .. }
+                | SkipNotKey { .. } => pending.extend([next, next + 2]),
+                _ => pending.push(next),
+            }
+        }
+
+        ProfileReport {
+            profile,
+            memory,
+            rom,
+            code,
+        }
+    }
+
+    /// Instructions in the ROM that could run but never did, as address
+    /// ranges of consecutive instructions
+    pub fn never_executed(&self) -> Vec<RangeInclusive<u16>> {
+        let mut ranges: Vec<RangeInclusive<u16>> = Vec::new();
+        for address in self.rom.clone().filter(|&a| self.code[a]) {
+            if self.profile.executed[address] > 0 {
+                continue;
+            }
+
+            let address = address as u16;
+            match ranges.last_mut() {
+                Some(range) if *range.end() + 2 == address => {
+                    *range = *range.start()..=address;
+                }
+                _ => ranges.push(address..=address),
+            }
+        }
+
+        ranges
+    }
+
+    /// Share of the ROM's instructions that ran at least once, from 0 to 1
+    pub fn coverage(&self) -> f64 {
+        let code = self.rom.clone().filter(|&a| self.code[a]);
+        let (total, executed) = code.fold((0, 0), |(total, executed), address| {
+            let ran = self.profile.executed[address] > 0;
+            (total + 1, executed + ran as usize)
+        });
+
+        match total {
+            0 => 0.0,
+            _ => executed as f64 / total as f64,
+        }
+    }
+
+    /// Every instruction of the ROM with how often it ran (`-` for never),
+    /// subroutines with their call counts and data as address ranges
+    pub fn annotated_disassembly(&self) -> String {
+        let mut out = String::new();
+        let mut address = *self.rom.start();
+        while address <= *self.rom.end() {
+            if !self.code[address] {
+                let start = address;
+                while address <= *self.rom.end() && !self.code[address] {
+                    address += 1;
+                }
+                let len = address - start;
+                out.push_str(&format!(
+                    "{:>10}  {:03X}-{:03X}  data, {} bytes\n",
+                    "",
+                    start,
+                    address - 1,
+                    len
+                ));
+                continue;
+            }
+
+            let calls = self.profile.calls[address];
+            if calls > 0 {
+                out.push_str(&format!("\nsub_{:03X}: called {} times\n", address, calls));
+            }
+            let executed = match self.profile.executed[address] {
+                0 => "-".to_string(),
+                count => count.to_string(),
+            };
+            let opcode = u16::from_be_bytes([self.memory[address], self.memory[address + 1]]);
+            out.push_str(&format!(
+                "{:>10}  {:03X}  {:04X}  {}\n",
+                executed,
+                address,
+                opcode,
+                disassemble_at(self.memory, address)
+            ));
+            address += 2;
+        }
+
+        out
+    }
+}
+
+impl fmt::Display for ProfileReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.profile.total();
+        let share = |count: u64| format!("{:.1}%", 100.0 * count as f64 / total.max(1) as f64);
+
+        writeln!(
+            f,
+            "{} instructions, {:.1}% of the ROM's code executed",
+            total,
+            100.0 * self.coverage()
+        )?;
+
+        let mut table = Table::new();
+        table.set_header(vec!["Address", "Count", "Share", "Instruction"]);
+        for (address, count) in self.profile.hot_addresses().into_iter().take(HOT_ADDRESSES) {
+            table.add_row(vec![
+                format!("{:03X}", address),
+                count.to_string(),
+                share(count),
+                disassemble_at(self.memory, address as usize),
+            ]);
+        }
+        writeln!(f, "{}", table)?;
+
+        let mut table = Table::new();
+        table.set_header(vec!["Opcode", "Count", "Share"]);
+        for (pattern, count) in self.profile.opcodes() {
+            table.add_row(vec![pattern.to_string(), count.to_string(), share(count)]);
+        }
+        writeln!(f, "{}", table)?;
+
+        let mut table = Table::new();
+        table.set_header(vec!["Subroutine", "Calls"]);
+        for (address, calls) in self.profile.subroutines() {
+            table.add_row(vec![format!("{:03X}", address), calls.to_string()]);
+        }
+        writeln!(f, "{}", table)?;
+
+        let mut table = Table::new();
+        table.set_header(vec!["Never executed", "Instructions"]);
+        for range in self.never_executed() {
+            table.add_row(vec![
+                format!("{:03X}-{:03X}", range.start(), range.end() + 1),
+                ((range.end() - range.start()) / 2 + 1).to_string(),
+            ]);
+        }
+        write!(f, "{}", table)
+    }
+}
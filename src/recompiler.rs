@@ -0,0 +1,196 @@
+use std::ops::RangeInclusive;
+
+use crate::{Chip8, Engine, Instruction};
+
+/// Upper bound on the number of instructions translated into one block
+const MAX_BLOCK_LEN: usize = 64;
+
+type Op<T> = Box<dyn Fn(&mut Chip8, &mut T)>;
+
+struct Block<T> {
+    /// Address of the last byte covered by the block
+    end: usize,
+    /// Translated instructions with the instruction they came from
+    ops: Vec<(Instruction, Op<T>)>,
+}
+
+/// Alternative to calling [`Chip8::emulate_cycle`] in a loop.
+///
+/// Straight-line runs of instructions (basic blocks) are translated once into
+/// chains of closures with their operands already bound, so running them again
+/// skips fetching and decoding. Blocks end at anything that changes control
+/// flow or writes memory, and blocks overlapping written memory are dropped,
+/// so self-modifying code behaves the same as in the interpreter.
+pub struct Recompiler<T> {
+    /// Blocks keyed by start address
+    blocks: Vec<Option<Block<T>>>,
+}
+
+impl<T: Engine> Recompiler<T> {
+    pub fn new() -> Self {
+        Recompiler {
+            blocks: (0..4096).map(|_| None).collect(),
+        }
+    }
+
+    /// Executes exactly `cycles` instructions, same as calling
+    /// [`Chip8::emulate_cycle`] `cycles` times.
+    pub fn run(&mut self, emulator: &mut Chip8, engine: &mut T, cycles: usize) {
+        let mut executed = 0;
+        while executed < cycles {
+            // memory may also change between calls, through `load_game` or the interpreter
+            if let Some(written) = emulator.take_written() {
+                self.invalidate(written);
+            }
+
+            let pc = emulator.regs.pc & 0xFFF;
+            // an instruction wrapping around to 000 is left to the interpreter
+            if pc == 0xFFF {
+                emulator.emulate_cycle(engine);
+                executed += 1;
+            } else {
+                let block = self.blocks[pc as usize].get_or_insert_with(|| compile(emulator, pc));
+                for (instruction, op) in block.ops.iter().take(cycles - executed) {
+                    emulator.count(*instruction);
+                    op(emulator, engine);
+                    executed += 1;
+                }
+            }
+        }
+    }
+
+    /// Drops every translated block
+    pub fn clear(&mut self) {
+        self.blocks.iter_mut().for_each(|block| *block = None);
+    }
+
+    fn invalidate(&mut self, written: RangeInclusive<usize>) {
+        // only blocks starting at most one block length before the write can overlap it
+        let first = written.start().saturating_sub(MAX_BLOCK_LEN * 2);
+        let last = (*written.end()).min(self.blocks.len() - 1);
+
+        for slot in &mut self.blocks[first..=last] {
+            if matches!(slot, Some(block) if block.end >= *written.start()) {
+                *slot = None;
+            }
+        }
+    }
+}
+
+impl<T: Engine> Default for Recompiler<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn compile<T: Engine>(emulator: &Chip8, start: u16) -> Block<T> {
+    let mem = &emulator.mem;
+    let mut ops = Vec::new();
+    let mut address = start as usize;
+
+    while ops.len() < MAX_BLOCK_LEN && address + 1 < mem.len() {
+        let opcode = u16::from_be_bytes([mem[address], mem[address + 1]]);
+        let instruction = match Instruction::decode(opcode) {
+            Some(instruction) => instruction,
+            None if ops.is_empty() => panic!("unknown instruction {:#06X}", opcode),
+            // leave it to the next block, it may never be reached
+            None => break,
+        };
+
+        ops.push((instruction, translate(instruction)));
+        address += 2;
+
+        if ends_block(instruction) {
+            break;
+        }
+    }
+    assert!(
+        !ops.is_empty(),
+        "program counter out of memory {:#06X}",
+        start
+    );
+
+    Block {
+        end: address - 1,
+        ops,
+    }
+}
+
+fn ends_block(instruction: Instruction) -> bool {
+    use Instruction::*;
+
+    matches!(
+        instruction,
+        Sys { .. }
+            | Ret
+            | Jump { .. }
+            | Call { .. }
+            | SkipEqImm { .. }
+            | SkipNeImm { .. }
+            | SkipEqReg { .. }
+            | SkipNeReg { .. }
+            | JumpV0 { .. }
+            | SkipKey { .. }
+            | SkipNotKey { .. }
+            | WaitKey { .. }
+            | Bcd { .. }
+            | StoreRegs { .. }
+    )
+}
+
+/// The most common instructions get their own closure, everything else
+/// goes through [`Chip8::execute`] with the decoded instruction bound.
+fn translate<T: Engine>(instruction: Instruction) -> Op<T> {
+    use Instruction::*;
+
+    match instruction {
+        LoadImm { x, nn } => Box::new(move |chip, _| {
+            chip.regs.v[x as usize] = nn;
+            next(chip, 2);
+        }),
+        AddImm { x, nn } => Box::new(move |chip, _| {
+            chip.regs.v[x as usize] = chip.regs.v[x as usize].wrapping_add(nn);
+            next(chip, 2);
+        }),
+        Load { x, y } => Box::new(move |chip, _| {
+            chip.regs.v[x as usize] = chip.regs.v[y as usize];
+            next(chip, 2);
+        }),
+        LoadI { nnn } => Box::new(move |chip, _| {
+            chip.regs.i = nnn;
+            next(chip, 2);
+        }),
+        Or { x, y } => Box::new(move |chip, _| {
+            chip.regs.v[x as usize] |= chip.regs.v[y as usize];
+            chip.reset_vf();
+            next(chip, 2);
+        }),
+        And { x, y } => Box::new(move |chip, _| {
+            chip.regs.v[x as usize] &= chip.regs.v[y as usize];
+            chip.reset_vf();
+            next(chip, 2);
+        }),
+        Xor { x, y } => Box::new(move |chip, _| {
+            chip.regs.v[x as usize] ^= chip.regs.v[y as usize];
+            chip.reset_vf();
+            next(chip, 2);
+        }),
+        Jump { nnn } => Box::new(move |chip, _| {
+            chip.regs.pc = nnn;
+        }),
+        SkipEqImm { x, nn } => Box::new(move |chip, _| {
+            let skip = chip.regs.v[x as usize] == nn;
+            next(chip, if skip { 4 } else { 2 });
+        }),
+        SkipNeImm { x, nn } => Box::new(move |chip, _| {
+            let skip = chip.regs.v[x as usize] != nn;
+            next(chip, if skip { 4 } else { 2 });
+        }),
+        _ => Box::new(move |chip, engine| chip.execute(instruction, engine)),
+    }
+}
+
+/// Moves PC forward, wrapping around at the end of memory
+fn next(chip: &mut Chip8, bytes: u16) {
+    chip.regs.pc = chip.regs.pc.wrapping_add(bytes) & 0xFFF;
+}
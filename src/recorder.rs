@@ -0,0 +1,186 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{engines::PixelBuf, save_png, Palette};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Frames are captured once per emulated frame
+const FPS: u64 = 60;
+
+enum Sink {
+    Gif {
+        encoder: gif::Encoder<BufWriter<File>>,
+        /// Last captured frame, written once we know how long it stays on screen
+        pending: Option<(Vec<u8>, u64)>,
+    },
+    PngSequence(PathBuf),
+    Y4m(BufWriter<File>),
+}
+
+/// Records gameplay, the format is picked from the path:
+/// `.gif` makes an animated GIF, `.y4m` a raw YUV4MPEG2 stream and
+/// anything else is a directory to fill with numbered PNGs.
+pub struct Recorder {
+    sink: Sink,
+    scale: usize,
+    palette: Palette,
+    frames: u64,
+}
+
+impl Recorder {
+    pub fn create(path: impl AsRef<Path>, scale: u32, palette: Palette) -> Result<Self> {
+        let path = path.as_ref();
+        let scale = scale.max(1) as usize;
+        let width = PixelBuf::WIDTH * scale;
+        let height = PixelBuf::HEIGHT * scale;
+
+        let sink = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gif") => {
+                let mut colors = Vec::new();
+                for color in [palette.background, palette.foreground] {
+                    colors.extend_from_slice(&color.to_be_bytes()[1..]);
+                }
+
+                let file = BufWriter::new(File::create(path)?);
+                let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &colors)?;
+                encoder.set_repeat(gif::Repeat::Infinite)?;
+
+                Sink::Gif {
+                    encoder,
+                    pending: None,
+                }
+            }
+            Some("y4m") => {
+                let mut file = BufWriter::new(File::create(path)?);
+                writeln!(
+                    file,
+                    "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    width, height, FPS
+                )?;
+
+                Sink::Y4m(file)
+            }
+            _ => {
+                std::fs::create_dir_all(path)?;
+                Sink::PngSequence(path.to_owned())
+            }
+        };
+
+        Ok(Recorder {
+            sink,
+            scale,
+            palette,
+            frames: 0,
+        })
+    }
+
+    pub fn capture(&mut self, framebuffer: &PixelBuf) -> Result<()> {
+        match &mut self.sink {
+            Sink::Gif { encoder, pending } => {
+                let indices = scaled(framebuffer, self.scale, 0, 1);
+                match pending {
+                    // nothing changed, keep the previous frame on screen longer
+                    Some((previous, _)) if *previous == indices => {}
+                    _ => {
+                        if let Some((previous, start)) = pending.take() {
+                            write_gif_frame(encoder, self.scale, previous, start, self.frames)?;
+                        }
+                        *pending = Some((indices, self.frames));
+                    }
+                }
+            }
+            Sink::PngSequence(dir) => {
+                let path = dir.join(format!("frame-{:06}.png", self.frames));
+                save_png(framebuffer, self.scale as u32, &self.palette, path)?;
+            }
+            Sink::Y4m(file) => {
+                let [y_on, u_on, v_on] = yuv(self.palette.foreground);
+                let [y_off, u_off, v_off] = yuv(self.palette.background);
+
+                file.write_all(b"FRAME\n")?;
+                file.write_all(&scaled(framebuffer, self.scale, y_off, y_on))?;
+                file.write_all(&scaled(framebuffer, self.scale, u_off, u_on))?;
+                file.write_all(&scaled(framebuffer, self.scale, v_off, v_on))?;
+            }
+        }
+
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Flushes the last frame and closes the file
+    pub fn finish(self) -> Result<()> {
+        match self.sink {
+            Sink::Gif {
+                mut encoder,
+                pending,
+            } => {
+                if let Some((previous, start)) = pending {
+                    write_gif_frame(&mut encoder, self.scale, previous, start, self.frames)?;
+                }
+                // writes the trailer
+                encoder.into_inner()?.flush()?;
+            }
+            Sink::PngSequence(_) => {}
+            Sink::Y4m(mut file) => file.flush()?,
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes a frame shown from frame number `start` until `end`.
+///
+/// GIF delays are in hundredths of a second, so each frame gets the
+/// rounded difference of its start and end time to avoid drifting.
+fn write_gif_frame(
+    encoder: &mut gif::Encoder<BufWriter<File>>,
+    scale: usize,
+    indices: Vec<u8>,
+    start: u64,
+    end: u64,
+) -> Result<()> {
+    let centiseconds = |frame: u64| (frame * 100 + FPS / 2) / FPS;
+    let frame = gif::Frame {
+        width: (PixelBuf::WIDTH * scale) as u16,
+        height: (PixelBuf::HEIGHT * scale) as u16,
+        delay: (centiseconds(end) - centiseconds(start)) as u16,
+        buffer: Cow::Owned(indices),
+        ..Default::default()
+    };
+    encoder.write_frame(&frame)?;
+
+    Ok(())
+}
+
+/// One byte per output pixel, `off` for unset and `on` for set pixels
+fn scaled(framebuffer: &PixelBuf, scale: usize, off: u8, on: u8) -> Vec<u8> {
+    let width = framebuffer.width() * scale;
+    let height = framebuffer.height() * scale;
+
+    let mut bytes = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            let set = framebuffer.pixel(x / scale, y / scale);
+            bytes.push(if set { on } else { off });
+        }
+    }
+
+    bytes
+}
+
+/// BT.601 studio swing conversion of `0xRRGGBB`
+fn yuv(color: u32) -> [u8; 3] {
+    let [_, r, g, b] = color.to_be_bytes().map(|c| c as f32);
+
+    let y = 16.0 + 0.257 * r + 0.504 * g + 0.098 * b;
+    let u = 128.0 - 0.148 * r - 0.291 * g + 0.439 * b;
+    let v = 128.0 + 0.439 * r - 0.368 * g - 0.071 * b;
+
+    [y, u, v].map(|c| c.round() as u8)
+}
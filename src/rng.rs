@@ -0,0 +1,102 @@
+use std::str::FromStr;
+
+/// Source of random numbers for CXNN
+pub trait Rng {
+    fn next_u8(&mut self) -> u8;
+
+    /// Current state if the generator is deterministic,
+    /// used to tell apart and reproduce runs
+    fn state(&self) -> Option<u64>;
+
+    /// Continues from a state returned by `state`
+    fn set_state(&mut self, state: u64);
+}
+
+/// Default generator, xorshift64*
+#[derive(Debug, Clone)]
+pub struct XorShiftRng {
+    state: u64,
+}
+
+impl XorShiftRng {
+    pub const DEFAULT_SEED: u64 = 0x2545_F491_4F6C_DD1D;
+
+    pub fn new(seed: u64) -> Self {
+        XorShiftRng {
+            // xorshift gets stuck on zero
+            state: if seed == 0 { Self::DEFAULT_SEED } else { seed },
+        }
+    }
+}
+
+impl Default for XorShiftRng {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_SEED)
+    }
+}
+
+impl Rng for XorShiftRng {
+    fn next_u8(&mut self) -> u8 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        (self.state.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 56) as u8
+    }
+
+    fn state(&self) -> Option<u64> {
+        Some(self.state)
+    }
+
+    fn set_state(&mut self, state: u64) {
+        *self = Self::new(state);
+    }
+}
+
+/// Seed given on the command line, a number or `entropy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seed {
+    Fixed(u64),
+    Entropy,
+}
+
+impl Seed {
+    /// The fixed seed, or one drawn from the operating system for `Entropy`,
+    /// which repeats the run when given as `--seed`
+    pub fn value(self) -> u64 {
+        match self {
+            Seed::Fixed(seed) => seed,
+            Seed::Entropy => {
+                let mut bytes = [0; 8];
+                getrandom::getrandom(&mut bytes).expect("OS entropy source is unavailable");
+                u64::from_le_bytes(bytes)
+            }
+        }
+    }
+
+    pub fn into_rng(self) -> Box<dyn Rng> {
+        Box::new(XorShiftRng::new(self.value()))
+    }
+}
+
+impl Default for Seed {
+    fn default() -> Self {
+        Seed::Fixed(XorShiftRng::DEFAULT_SEED)
+    }
+}
+
+impl FromStr for Seed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "entropy" {
+            return Ok(Seed::Entropy);
+        }
+
+        let seed = match s.strip_prefix("0x") {
+            Some(hex) => u64::from_str_radix(hex, 16),
+            None => s.parse(),
+        };
+        seed.map(Seed::Fixed)
+            .map_err(|_| "expected a number or entropy".to_owned())
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use crate::{vip, Chip8, Engine, Instruction};
+
+/// How the instructions of a frame are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timing {
+    /// Every instruction takes the same time, see [`Scheduler::instructions_per_frame`]
+    #[default]
+    Fixed,
+    /// Instructions cost their COSMAC VIP machine cycles out of a per-frame
+    /// budget and drawing waits for the next frame
+    Vip,
+}
+
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fixed" => Ok(Timing::Fixed),
+            "vip" => Ok(Timing::Vip),
+            _ => Err("unknown timing, expected fixed or vip".to_owned()),
+        }
+    }
+}
+
+/// How fast the CPU and the timers run, shared by every engine
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    pub cpu_hz: u32,
+    pub timer_hz: u32,
+    /// Instructions per frame (timer tick), overrides `cpu_hz` when set
+    pub ipf: Option<u32>,
+    pub timing: Timing,
+}
+
+/// What one frame leaves over for the next, kept by whoever runs the frames
+/// so that it is not part of the machine state
+#[derive(Debug, Default, Clone)]
+pub struct FrameState {
+    /// Part of `Scheduler::cpu_hz` that did not make a whole instruction
+    /// in the previous frames, see `Scheduler::frame_instructions`
+    instruction_remainder: u32,
+    /// Machine cycles the last instruction of the previous VIP frame ran
+    /// past its end, taken from the next frame
+    cycle_debt: u32,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler {
+            cpu_hz: 600,
+            timer_hz: 60,
+            ipf: None,
+            timing: Timing::default(),
+        }
+    }
+}
+
+impl Scheduler {
+    /// Whole instructions in a frame, at least one. When `cpu_hz` is not a
+    /// multiple of `timer_hz`, `run_frame` runs one more every few frames
+    pub fn instructions_per_frame(&self) -> u32 {
+        self.ipf
+            .unwrap_or(self.cpu_hz / self.timer_hz.max(1))
+            .max(1)
+    }
+
+    /// Instructions to run in the next frame. Without `ipf` the part of
+    /// `cpu_hz` that does not divide into frames is carried over in
+    /// `state`, so the average is exactly `cpu_hz`, even below one
+    /// instruction per frame.
+    pub(crate) fn frame_instructions(&self, state: &mut FrameState) -> u32 {
+        if let Some(ipf) = self.ipf {
+            return ipf.max(1);
+        }
+
+        let timer_hz = self.timer_hz.max(1);
+        let total = state.instruction_remainder + self.cpu_hz.max(1);
+        state.instruction_remainder = total % timer_hz;
+        total / timer_hz
+    }
+
+    pub fn frame_period(&self) -> Duration {
+        Duration::from_secs(1) / self.timer_hz.max(1)
+    }
+
+    pub fn pacer(&self) -> FramePacer {
+        FramePacer::new(self.frame_period())
+    }
+
+    /// Runs one frame worth of instructions and ticks the timers once,
+    /// as fast as possible. `state` carries what is left over to the next frame
+    pub fn run_frame<T: Engine>(
+        &self,
+        state: &mut FrameState,
+        emulator: &mut Chip8,
+        engine: &mut T,
+    ) {
+        match self.timing {
+            Timing::Fixed => {
+                for _ in 0..self.frame_instructions(state) {
+                    emulator.emulate_cycle(engine);
+                }
+            }
+            Timing::Vip => Self::run_vip_frame(state, emulator, engine),
+        }
+
+        emulator.decrement_timers();
+    }
+
+    fn run_vip_frame<T: Engine>(state: &mut FrameState, emulator: &mut Chip8, engine: &mut T) {
+        // no instruction takes a whole frame, so the debt is always smaller
+        let mut budget = vip::CYCLES_PER_FRAME - std::mem::take(&mut state.cycle_debt);
+        loop {
+            let pc = emulator.pc();
+            let instruction = emulator.current_instruction();
+            emulator.emulate_cycle(engine);
+
+            // only reached for valid instructions, emulate_cycle panics otherwise
+            let Some(instruction) = instruction else {
+                break;
+            };
+            // addresses wrap at the end of memory
+            let skipped = emulator.pc() == (pc + 4) & 0xFFF;
+            let cost = vip::cycles(instruction, skipped);
+
+            if cost >= budget {
+                state.cycle_debt = cost - budget;
+                break;
+            }
+            budget -= cost;
+
+            // the VIP interpreter waits for the display interrupt after drawing,
+            // so the rest of the frame is spent idle
+            if matches!(instruction, Instruction::Draw { .. }) {
+                break;
+            }
+        }
+    }
+}
+
+/// Time source of a `FramePacer`, replaced by a fake one in tests
+pub trait Clock {
+    fn now(&self) -> Instant;
+
+    fn sleep(&self, duration: Duration);
+}
+
+/// The monotonic system clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+impl<C: Clock> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        (**self).sleep(duration);
+    }
+}
+
+/// Keeps frames on a fixed grid of a monotonic clock, so sleeping too long
+/// in one frame is made up in the next ones instead of accumulating.
+pub struct FramePacer<C: Clock = SystemClock> {
+    clock: C,
+    period: Duration,
+    next_frame: Instant,
+    /// Most frames to run at once when behind, the rest are dropped
+    pub max_catch_up: u32,
+    dropped_frames: u64,
+}
+
+impl FramePacer {
+    pub fn new(period: Duration) -> Self {
+        Self::with_clock(period, SystemClock)
+    }
+}
+
+impl<C: Clock> FramePacer<C> {
+    pub fn with_clock(period: Duration, clock: C) -> Self {
+        FramePacer {
+            next_frame: clock.now(),
+            clock,
+            period,
+            max_catch_up: 4,
+            dropped_frames: 0,
+        }
+    }
+
+    /// Sleeps until the next frame is due and returns how many frames
+    /// should be emulated now, more than one if we fell behind.
+    pub fn wait(&mut self) -> u32 {
+        let now = self.clock.now();
+        if now < self.next_frame {
+            self.clock.sleep(self.next_frame - now);
+        }
+
+        let now = self.clock.now();
+        let late = now.saturating_duration_since(self.next_frame);
+        let due = (late.as_nanos() / self.period.as_nanos().max(1)) as u64 + 1;
+        if due > self.max_catch_up as u64 {
+            self.dropped_frames += due - self.max_catch_up as u64;
+            self.next_frame = now + self.period;
+            return self.max_catch_up;
+        }
+
+        self.next_frame += self.period * due as u32;
+        due as u32
+    }
+
+    /// Frames skipped so far because emulation could not keep up
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+}
@@ -0,0 +1,56 @@
+use std::{fs::File, io::BufWriter, io::Write, path::Path};
+
+use crate::{engines::PixelBuf, Palette};
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Encodes `framebuffer` as an RGB PNG, every pixel becomes a `scale`x`scale` square
+pub fn write_png(
+    framebuffer: &PixelBuf,
+    scale: u32,
+    palette: &Palette,
+    writer: impl Write,
+) -> Result<()> {
+    let scale = scale.max(1) as usize;
+    let width = framebuffer.width() * scale;
+    let height = framebuffer.height() * scale;
+
+    let mut pixels = Vec::with_capacity(width * height);
+    for y in 0..height {
+        for x in 0..width {
+            pixels.push(palette.color(framebuffer.pixel(x / scale, y / scale) as u8));
+        }
+    }
+
+    write_rgb_png(width, height, &pixels, writer)
+}
+
+/// Encodes `0xRRGGBB` pixels, row by row, as an RGB PNG
+pub(crate) fn write_rgb_png(
+    width: usize,
+    height: usize,
+    pixels: &[u32],
+    writer: impl Write,
+) -> Result<()> {
+    let data: Vec<u8> = pixels
+        .iter()
+        .flat_map(|color| color.to_be_bytes().into_iter().skip(1))
+        .collect();
+
+    let mut encoder = png::Encoder::new(writer, width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)?;
+
+    Ok(())
+}
+
+pub fn save_png(
+    framebuffer: &PixelBuf,
+    scale: u32,
+    palette: &Palette,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let file = File::create(path)?;
+    write_png(framebuffer, scale, palette, BufWriter::new(file))
+}
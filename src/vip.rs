@@ -0,0 +1,53 @@
+//! Approximate COSMAC VIP instruction timings, after Laurence Scotford's
+//! disassembly of the original interpreter. Costs are in machine cycles
+//! (8 clock cycles of the 1.76 MHz CDP1802).
+
+use crate::Instruction;
+
+/// Machine cycles between two 60 Hz display interrupts
+pub(crate) const CYCLES_PER_FRAME: u32 = 3668;
+
+/// Fetching and dispatching any instruction
+const FETCH: u32 = 40;
+
+/// Cost of `instruction`, `skipped` tells if a skip instruction did skip
+pub(crate) fn cycles(instruction: Instruction, skipped: bool) -> u32 {
+    use Instruction::*;
+
+    let skip = if skipped { 4 } else { 0 };
+    let execute = match instruction {
+        // machine code routines, nothing to go on
+        Sys { .. } => 0,
+        Cls => 3078,
+        Ret => 10,
+        Jump { .. } => 12,
+        Call { .. } => 26,
+        SkipEqImm { .. } | SkipNeImm { .. } => 10 + skip,
+        SkipEqReg { .. } | SkipNeReg { .. } => 14 + skip,
+        LoadImm { .. } => 6,
+        AddImm { .. } => 10,
+        Load { .. }
+        | Or { .. }
+        | And { .. }
+        | Xor { .. }
+        | Add { .. }
+        | Sub { .. }
+        | Shr { .. }
+        | SubN { .. }
+        | Shl { .. } => 44,
+        LoadI { .. } => 12,
+        JumpV0 { .. } => 22,
+        Rand { .. } => 36,
+        Draw { n, .. } => 26 + 46 * n as u32,
+        SkipKey { .. } | SkipNotKey { .. } => 14 + skip,
+        LoadDelay { .. } => 10,
+        WaitKey { .. } => 18,
+        SetDelay { .. } | SetSound { .. } => 10,
+        AddI { .. } => 16,
+        LoadFont { .. } => 20,
+        Bcd { x: _ } => 84,
+        StoreRegs { x } | LoadRegs { x } => 14 + 14 * (x as u32 + 1),
+    };
+
+    FETCH + execute
+}
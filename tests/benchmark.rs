@@ -0,0 +1,53 @@
+use chip_8::{run_benchmark, Quircks, Scheduler, Seed, Timing};
+
+fn maze() -> Vec<u8> {
+    std::fs::read("roms/maze.ch8").unwrap()
+}
+
+#[test]
+fn recompiler_runs_the_same_frames() {
+    let scheduler = Scheduler {
+        cpu_hz: 700,
+        ..Default::default()
+    };
+
+    let interpreted = run_benchmark(
+        &maze(),
+        10_000,
+        &scheduler,
+        Quircks::default(),
+        Seed::Fixed(1),
+        false,
+    );
+    let recompiled = run_benchmark(
+        &maze(),
+        10_000,
+        &scheduler,
+        Quircks::default(),
+        Seed::Fixed(1),
+        true,
+    );
+
+    assert_eq!(
+        interpreted.instructions_per_frame(),
+        recompiled.instructions_per_frame()
+    );
+}
+
+#[test]
+#[should_panic(expected = "fixed timing")]
+fn recompiler_rejects_vip_timing() {
+    let scheduler = Scheduler {
+        timing: Timing::Vip,
+        ..Default::default()
+    };
+
+    run_benchmark(
+        &maze(),
+        100,
+        &scheduler,
+        Quircks::default(),
+        Seed::Fixed(1),
+        true,
+    );
+}
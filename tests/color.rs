@@ -0,0 +1,69 @@
+use chip_8::{
+    engines::{ansi16, ansi256, Cell, ColorDepth, PixelBuf, TermRender},
+    Palette,
+};
+use crossterm::style::Color;
+
+#[test]
+fn nearest_ansi16_color() {
+    assert_eq!(ansi16(0x00_00_00), 0);
+    assert_eq!(ansi16(0xFF_FF_FF), 15);
+    assert_eq!(ansi16(0xFF_B0_00), 11);
+    assert_eq!(ansi16(0x0F_38_0F), 0);
+    assert_eq!(ansi16(0x33_FF_66), 10);
+}
+
+#[test]
+fn nearest_ansi256_color() {
+    assert_eq!(ansi256(0x00_00_00), 16);
+    assert_eq!(ansi256(0xFF_FF_FF), 231);
+    assert_eq!(ansi256(0xFF_00_00), 196);
+    // in between two cube levels, the gray ramp is closer
+    assert_eq!(ansi256(0x80_80_80), 244);
+    assert_eq!(ansi256(0xFF_CC_00), 220);
+}
+
+#[test]
+fn depth_picks_the_escape_code() {
+    assert_eq!(ColorDepth::Mono.color(0xFF_CC_00), None);
+    assert_eq!(ColorDepth::Ansi16.color(0xFF_CC_00), Some(Color::Yellow));
+    assert_eq!(
+        ColorDepth::Ansi256.color(0xFF_CC_00),
+        Some(Color::AnsiValue(220))
+    );
+    assert_eq!(
+        ColorDepth::TrueColor.color(0xFF_CC_00),
+        Some(Color::Rgb {
+            r: 0xFF,
+            g: 0xCC,
+            b: 0x00
+        })
+    );
+
+    assert_eq!("none".parse(), Ok(ColorDepth::Mono));
+    assert_eq!("256".parse(), Ok(ColorDepth::Ansi256));
+    assert!("88".parse::<ColorDepth>().is_err());
+}
+
+#[test]
+fn half_blocks_color_both_pixels() {
+    let mut framebuffer = PixelBuf::new();
+    // bottom pixel of the first cell
+    framebuffer.draw_sprite(0, 1, 1, &[0b1000_0000]);
+    let palette = Palette::OCTO;
+
+    let cells = TermRender::HalfBlock.cells(&framebuffer, &palette);
+    assert_eq!(cells.len(), 16);
+    assert_eq!(
+        cells[0][0],
+        Cell {
+            ch: '▀',
+            foreground: palette.background,
+            background: palette.foreground,
+        }
+    );
+
+    let cells = TermRender::Ascii.cells(&framebuffer, &palette);
+    assert_eq!(cells[1][0].ch, '#');
+    assert_eq!(cells[1][0].foreground, palette.foreground);
+}
@@ -0,0 +1,147 @@
+//! Runs test ROMs headless and compares the final screen with the images in
+//! `tests/golden`, one per ROM and quirk profile that draws something different.
+//!
+//! Set `UPDATE_GOLDEN=1` to write the images instead, then check them by eye.
+//! The images are plain PBM files, `1` being a lit pixel.
+
+use std::path::{Path, PathBuf};
+
+use chip_8::{engines::HeadlessEngine, Chip8, Engine, FrameState, Quircks, Scheduler, Timing};
+
+/// Quirks and timing of a CHIP-8 implementation
+struct QuirkProfile {
+    name: &'static str,
+    quircks: Quircks,
+    timing: Timing,
+}
+
+/// The original COSMAC VIP interpreter
+const VIP: QuirkProfile = QuirkProfile {
+    name: "vip",
+    quircks: Quircks {
+        load_store: true,
+        shift: false,
+        jump: false,
+        vf_reset: true,
+    },
+    timing: Timing::Vip,
+};
+
+/// SUPER-CHIP and most interpreters written since
+const MODERN: QuirkProfile = QuirkProfile {
+    name: "modern",
+    quircks: Quircks {
+        load_store: false,
+        shift: true,
+        jump: true,
+        vf_reset: false,
+    },
+    timing: Timing::Fixed,
+};
+
+struct Case {
+    rom: &'static str,
+    frames: usize,
+}
+
+impl Case {
+    const fn new(rom: &'static str, frames: usize) -> Self {
+        Case { rom, frames }
+    }
+}
+
+fn run(rom: &[u8], case: &Case, profile: &QuirkProfile) -> String {
+    let mut chip = Chip8::new();
+    chip.quircks = profile.quircks;
+    chip.load_game(rom);
+    let scheduler = Scheduler {
+        timing: profile.timing,
+        ..Default::default()
+    };
+
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+    for _ in 0..case.frames {
+        scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    }
+
+    let framebuffer = engine.framebuffer();
+    let mut pbm = format!("P1\n{} {}\n", framebuffer.width(), framebuffer.height());
+    for y in 0..framebuffer.height() {
+        for x in 0..framebuffer.width() {
+            pbm.push(if framebuffer.pixel(x, y) { '1' } else { '0' });
+        }
+        pbm.push('\n');
+    }
+
+    pbm
+}
+
+/// Checks `case` against the golden image named for each profile,
+/// profiles drawing the same picture share one
+fn check(case: Case, expected: &[(QuirkProfile, &str)]) {
+    let path = Path::new("roms").join(case.rom);
+    let rom = std::fs::read(&path).unwrap();
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+
+    for (profile, golden) in expected {
+        let actual = run(&rom, &case, profile);
+        let golden = PathBuf::from(format!("tests/golden/{}.pbm", golden));
+        if update {
+            std::fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+
+        let expected = std::fs::read_to_string(&golden).unwrap_or_else(|_| {
+            panic!("{} is missing, run with UPDATE_GOLDEN=1", golden.display())
+        });
+        assert!(
+            actual == expected,
+            "{} with the {} profile does not match {}, it drew\n{}",
+            case.rom,
+            profile.name,
+            golden.display(),
+            // without the header, lit pixels as #
+            actual
+                .lines()
+                .skip(2)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .replace('0', ".")
+                .replace('1', "#"),
+        );
+    }
+}
+
+#[test]
+fn ibm_logo() {
+    check(
+        Case::new("IBM Logo.ch8", 60),
+        &[(VIP, "ibm-logo"), (MODERN, "ibm-logo")],
+    );
+}
+
+#[test]
+fn chip8_test_rom() {
+    check(
+        Case::new("chip8-test-rom.ch8", 120),
+        &[(VIP, "chip8-test-rom"), (MODERN, "chip8-test-rom")],
+    );
+}
+
+/// Divides with 8XY6, so the shift quirk changes the picture
+#[test]
+fn division_test() {
+    check(
+        Case::new("Division Test [Sergey Naydenov, 2010].ch8", 120),
+        &[(VIP, "division-test-vip"), (MODERN, "division-test-modern")],
+    );
+}
+
+#[test]
+fn sqrt_test() {
+    check(
+        Case::new("SQRT Test [Sergey Naydenov, 2010].ch8", 120),
+        &[(VIP, "sqrt-test"), (MODERN, "sqrt-test")],
+    );
+}
@@ -0,0 +1,49 @@
+use chip_8::EmulationControl;
+
+#[test]
+fn normal_speed_runs_due_frames() {
+    let mut control = EmulationControl::default();
+    assert_eq!(control.frames_to_run(1), 1);
+    assert_eq!(control.frames_to_run(3), 3);
+}
+
+#[test]
+fn slow_motion_accumulates_fractions() {
+    let mut control = EmulationControl::default();
+    control.slower();
+    control.slower();
+    assert_eq!(control.speed(), 0.25);
+
+    let frames: u32 = (0..8).map(|_| control.frames_to_run(1)).sum();
+    assert_eq!(frames, 2);
+}
+
+#[test]
+fn fast_forward_and_turbo() {
+    let mut control = EmulationControl::default();
+    control.faster();
+    assert_eq!(control.frames_to_run(1), 2);
+
+    control.set_turbo(true);
+    assert_eq!(control.frames_to_run(1), 8);
+
+    control.set_turbo(false);
+    control.reset_speed();
+    assert_eq!(control.frames_to_run(1), 1);
+}
+
+#[test]
+fn pause_and_frame_advance() {
+    let mut control = EmulationControl::default();
+    control.toggle_pause();
+    assert!(control.is_paused());
+    assert_eq!(control.frames_to_run(5), 0);
+
+    control.frame_advance();
+    control.frame_advance();
+    assert_eq!(control.frames_to_run(1), 2);
+    assert_eq!(control.frames_to_run(1), 0);
+
+    control.toggle_pause();
+    assert_eq!(control.frames_to_run(1), 1);
+}
@@ -0,0 +1,133 @@
+use chip_8::{
+    engines::HeadlessEngine, hex_view, Chip8, Command, Debugger, Location, Scheduler, Stop,
+};
+
+fn ibm_logo() -> Chip8 {
+    let mut emulator = Chip8::new();
+    emulator.load_game(&std::fs::read("roms/IBM Logo.ch8").unwrap());
+    emulator
+}
+
+#[test]
+fn commands_parse() {
+    assert_eq!("s".parse(), Ok(Command::Step(1)));
+    assert_eq!("step 10".parse(), Ok(Command::Step(10)));
+    assert_eq!("f 2".parse(), Ok(Command::Frame(2)));
+    assert_eq!("c".parse(), Ok(Command::Continue));
+    assert_eq!("b 0x21E".parse(), Ok(Command::Break(0x21E)));
+    assert_eq!("break $200".parse(), Ok(Command::Break(0x200)));
+    assert_eq!("d".parse(), Ok(Command::Delete(None)));
+    assert_eq!("d 21e".parse(), Ok(Command::Delete(Some(0x21E))));
+    assert_eq!("m i".parse(), Ok(Command::Memory(Location::I)));
+    assert_eq!(
+        "mem 300".parse(),
+        Ok(Command::Memory(Location::Address(0x300)))
+    );
+    assert_eq!("k a".parse(), Ok(Command::Key(0xA, 6)));
+    assert_eq!("key 5 20".parse(), Ok(Command::Key(5, 20)));
+    assert_eq!("q".parse(), Ok(Command::Quit));
+
+    assert!("b".parse::<Command>().is_err());
+    assert!("b 1000".parse::<Command>().is_err());
+    assert!("k 10".parse::<Command>().is_err());
+    assert!("jump 200".parse::<Command>().is_err());
+}
+
+#[test]
+fn starts_paused_and_steps() {
+    let mut emulator = ibm_logo();
+    let mut engine = HeadlessEngine::new(0);
+    let scheduler = Scheduler::default();
+    let mut debugger = Debugger::new();
+
+    debugger.run_frame(&mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.pc(), 0x200);
+
+    debugger.execute(Command::Step(3), &mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.pc(), 0x206);
+    assert_eq!(debugger.stopped(), Some(Stop::Step));
+}
+
+#[test]
+fn continue_stops_at_breakpoints() {
+    let mut emulator = ibm_logo();
+    let mut engine = HeadlessEngine::new(0);
+    let scheduler = Scheduler::default();
+    let mut debugger = Debugger::new();
+
+    debugger.execute(
+        Command::Break(0x20C),
+        &mut emulator,
+        &mut engine,
+        &scheduler,
+    );
+    debugger.execute(Command::Continue, &mut emulator, &mut engine, &scheduler);
+    debugger.run_frame(&mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.pc(), 0x20C);
+    assert_eq!(debugger.stopped(), Some(Stop::Breakpoint(0x20C)));
+
+    // continuing steps over the breakpoint it stopped at
+    debugger.execute(Command::Continue, &mut emulator, &mut engine, &scheduler);
+    debugger.run_frame(&mut emulator, &mut engine, &scheduler);
+    assert!(!debugger.is_paused());
+    assert_ne!(emulator.pc(), 0x20C);
+}
+
+#[test]
+fn frames_tick_the_timers() {
+    let mut emulator = Chip8::new();
+    // V0 = 10, DT = V0, loop
+    emulator.load_game(&[0x60, 0x0A, 0xF0, 0x15, 0x12, 0x04]);
+    let mut engine = HeadlessEngine::new(0);
+    let scheduler = Scheduler::default();
+    let mut debugger = Debugger::new();
+
+    debugger.execute(Command::Frame(3), &mut emulator, &mut engine, &scheduler);
+    assert_eq!(debugger.stopped(), Some(Stop::Frame));
+    assert_eq!(emulator.cpu_state().delay_timer, 7);
+}
+
+#[test]
+fn held_keys_are_released() {
+    let mut emulator = ibm_logo();
+    let mut engine = HeadlessEngine::new(0);
+    let scheduler = Scheduler::default();
+    let mut debugger = Debugger::new();
+
+    debugger.execute(Command::Key(0xA, 2), &mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.keypad(), 1 << 0xA);
+    debugger.execute(Command::Frame(1), &mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.keypad(), 1 << 0xA);
+    debugger.execute(Command::Frame(1), &mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.keypad(), 0);
+}
+
+#[test]
+fn hex_view_stops_at_the_end_of_memory() {
+    let memory: Vec<u8> = (0..=255).collect();
+    let lines = hex_view(&memory, 0xF8, 4, 4);
+    assert_eq!(lines, ["00F8  F8 F9 FA FB", "00FC  FC FD FE FF"]);
+}
+
+#[test]
+fn frames_follow_cpu_hz() {
+    let mut emulator = Chip8::new();
+    // 200: goto 200
+    emulator.load_game(&[0x12, 0x00]);
+    let mut engine = HeadlessEngine::new(0);
+    let mut debugger = Debugger::new();
+
+    let scheduler = Scheduler {
+        cpu_hz: 1000,
+        ..Default::default()
+    };
+    debugger.execute(Command::Frame(60), &mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.cycles(), 1000);
+
+    let scheduler = Scheduler {
+        cpu_hz: 30,
+        ..Default::default()
+    };
+    debugger.execute(Command::Frame(60), &mut emulator, &mut engine, &scheduler);
+    assert_eq!(emulator.cycles(), 1030);
+}
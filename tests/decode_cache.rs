@@ -0,0 +1,40 @@
+use chip_8::{engines::HeadlessEngine, Chip8};
+
+#[test]
+fn self_modifying_code_matches_without_cache() {
+    #[rustfmt::skip]
+    let program = [
+        0x60, 0x6A, // 200: V0 = 6A
+        0x71, 0x01, // 202: V1 += 1
+        0xA2, 0x0C, // 204: I = 20C
+        0xF1, 0x55, // 206: store V0..V1 at I, rewrites 20C to VA = V1
+        0xA2, 0x0F, // 208: I = 20F
+        0xF2, 0x33, // 20A: store V2 as BCD at I, rewrites 20E to VB = hundreds
+        0x6A, 0x00, // 20C: VA = 00
+        0x6B, 0x00, // 20E: VB = 00
+        0x00, 0x00, // 210: tens and ones, a 0NNN that does nothing
+        0x72, 0x37, // 212: V2 += 37
+        0x12, 0x02, // 214: goto 202
+    ];
+
+    let mut cached = Chip8::new();
+    cached.load_game(&program);
+    let mut uncached = Chip8::new();
+    uncached.set_decode_cache(false);
+    uncached.load_game(&program);
+    let mut engine = HeadlessEngine::new(0);
+
+    // V0 = 6A, then 41 times round the loop
+    for cycle in 0..1 + 41 * 10 {
+        cached.emulate_cycle(&mut engine);
+        uncached.emulate_cycle(&mut engine);
+        assert_eq!(cached.cpu_state(), uncached.cpu_state(), "cycle {}", cycle);
+        assert_eq!(cached.memory(), uncached.memory(), "cycle {}", cycle);
+    }
+
+    let state = cached.cpu_state();
+    assert_eq!(state.pc, 0x202);
+    assert_eq!(state.v[0xA], state.v[1]);
+    assert_eq!(state.v[0xB], state.v[2].wrapping_sub(0x37) / 100);
+    assert_ne!(state.v[0xB], 0);
+}
@@ -0,0 +1,53 @@
+use chip_8::{engines::PixelBuf, DisplayFilter, Palette, Phosphor};
+
+#[test]
+fn turned_off_pixels_fade_out() {
+    let mut framebuffer = PixelBuf::new();
+    let mut phosphor = Phosphor::new(0.5);
+
+    framebuffer.draw_sprite(0, 0, 1, &[0b1000_0000]);
+    phosphor.update(&framebuffer);
+    assert_eq!(phosphor.intensity(0, 0), 1.0);
+    assert_eq!(phosphor.intensity(1, 0), 0.0);
+
+    // XOR the pixel back off
+    framebuffer.draw_sprite(0, 0, 1, &[0b1000_0000]);
+    phosphor.update(&framebuffer);
+    assert_eq!(phosphor.intensity(0, 0), 0.5);
+    phosphor.update(&framebuffer);
+    assert_eq!(phosphor.intensity(0, 0), 0.25);
+
+    // dim pixels snap to the background instead of lingering
+    for _ in 0..4 {
+        phosphor.update(&framebuffer);
+    }
+    assert_eq!(phosphor.intensity(0, 0), 0.0);
+}
+
+#[test]
+fn faded_pixels_blend_palette_colors() {
+    let mut framebuffer = PixelBuf::new();
+    let mut phosphor = Phosphor::new(0.5);
+    let palette = Palette {
+        background: 0x00_00_00,
+        foreground: 0xFF_80_40,
+        ..Default::default()
+    };
+
+    framebuffer.draw_sprite(0, 0, 1, &[0b1000_0000]);
+    phosphor.update(&framebuffer);
+    assert_eq!(phosphor.color(0, 0, &palette), 0xFF_80_40);
+    assert_eq!(phosphor.color(1, 0, &palette), 0x00_00_00);
+
+    framebuffer.clear();
+    phosphor.update(&framebuffer);
+    assert_eq!(phosphor.color(0, 0, &palette), 0x80_40_20);
+}
+
+#[test]
+fn display_filters_parse() {
+    assert_eq!("none".parse(), Ok(DisplayFilter::None));
+    assert!("vblank".parse::<DisplayFilter>().is_err());
+    assert_eq!("phosphor".parse(), Ok(DisplayFilter::Phosphor));
+    assert!("crt".parse::<DisplayFilter>().is_err());
+}
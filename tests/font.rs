@@ -0,0 +1,78 @@
+use chip_8::{Canvas, CHAR_HEIGHT, CHAR_WIDTH};
+
+/// Renders `text` at scale 1 and returns the rows as `#` and `.`
+fn render(text: &str) -> Vec<String> {
+    let width = text.chars().count() * CHAR_WIDTH;
+    let mut buffer = vec![0; width * CHAR_HEIGHT];
+    let mut canvas = Canvas {
+        buffer: &mut buffer,
+        stride: width,
+        left: 0,
+        width,
+        height: CHAR_HEIGHT,
+    };
+    canvas.text(0, 0, text, 1, 1);
+
+    buffer
+        .chunks(width)
+        .map(|row| {
+            row.iter()
+                .map(|&p| if p == 1 { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn glyphs_are_drawn_in_cells() {
+    assert_eq!(
+        render("A1"),
+        [".#...#..", "#.#.##..", "###..#..", "#.#..#..", "#.#.###.", "........",]
+    );
+}
+
+#[test]
+fn lowercase_and_unknown_characters() {
+    assert_eq!(render("pc"), render("PC"));
+    assert_eq!(render("~"), render("é"));
+    assert_eq!(
+        render("~"),
+        ["##..", "..#.", ".#..", "....", ".#..", "...."]
+    );
+}
+
+#[test]
+fn text_is_scaled_and_clipped() {
+    // 10 columns, the canvas starts at column 2 and is 6 wide
+    let mut buffer = vec![0; 10 * 8];
+    let mut canvas = Canvas {
+        buffer: &mut buffer,
+        stride: 10,
+        left: 2,
+        width: 6,
+        height: 8,
+    };
+    canvas.text(1, 1, "11", 1, 2);
+
+    let rows: Vec<String> = buffer
+        .chunks(10)
+        .map(|row| {
+            row.iter()
+                .map(|&p| if p == 1 { '#' } else { '.' })
+                .collect()
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            "..........",
+            ".....##...",
+            ".....##...",
+            "...####...",
+            "...####...",
+            ".....##...",
+            ".....##...",
+            ".....##...",
+        ]
+    );
+}
@@ -0,0 +1,37 @@
+use chip_8::Instruction;
+
+#[test]
+fn decode_encode_round_trip() {
+    for opcode in 0..=u16::MAX {
+        if let Some(instruction) = Instruction::decode(opcode) {
+            assert_eq!(
+                instruction.encode(),
+                opcode,
+                "{:04X} decoded as {:?}",
+                opcode,
+                instruction
+            );
+            assert_eq!(Instruction::decode(instruction.encode()), Some(instruction));
+        }
+    }
+}
+
+#[test]
+fn invalid_opcodes_are_rejected() {
+    for opcode in [0x5121, 0x8008, 0x800F, 0x9AB1, 0xE09F, 0xF000, 0xF0FF] {
+        assert_eq!(Instruction::decode(opcode), None, "{:04X}", opcode);
+    }
+}
+
+#[test]
+fn shifts_are_not_confused() {
+    assert_eq!(
+        Instruction::decode(0x8126),
+        Some(Instruction::Shr { x: 1, y: 2 })
+    );
+    assert_eq!(
+        Instruction::decode(0x812E),
+        Some(Instruction::Shl { x: 1, y: 2 })
+    );
+    assert_eq!(Instruction::decode(0x8128), None);
+}
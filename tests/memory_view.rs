@@ -0,0 +1,104 @@
+use chip_8::{
+    engines::HeadlessEngine, Chip8, FrameState, MemoryView, Palette, Scheduler, RECENT_SPRITES,
+};
+
+const PALETTE: Palette = Palette {
+    background: 0x00_00_00,
+    foreground: 0xFF_FF_FF,
+    plane2: 0xFF_00_00,
+    blend: 0x00_00_FF,
+};
+
+/// 200: I = 208, 202: draw 2 rows, 204: I = 20C, 206: loop, 208: sprite
+const PROGRAM: [u8; 12] = [
+    0xA2, 0x08, 0xD0, 0x02, 0xA2, 0x0C, 0x12, 0x06, 0xF0, 0x90, 0x00, 0x00,
+];
+
+fn run(program: &[u8], frames: usize) -> Chip8 {
+    let mut chip = Chip8::new();
+    chip.load_game(program);
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+    let scheduler = Scheduler::default();
+    for _ in 0..frames {
+        scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    }
+
+    chip
+}
+
+#[test]
+fn jump_and_scroll() {
+    let mut view = MemoryView::new(1);
+    view.jump(0x2A5);
+    assert_eq!(view.start(), 0x2A0);
+
+    view.scroll(-1);
+    assert_eq!(view.start(), 0x280);
+    view.scroll(-100);
+    assert_eq!(view.start(), 0x000);
+    view.scroll(1000);
+    assert_eq!(view.start(), 0xFE0);
+
+    view.jump(0x5000);
+    assert_eq!(view.start(), 0xFE0);
+}
+
+#[test]
+fn size_covers_the_rest_of_memory() {
+    let mut view = MemoryView::new(2);
+    let (width, full_height) = view.size();
+    assert_eq!(width, 16 * 14 * 2);
+
+    view.jump(0xE00);
+    let (_, height) = view.size();
+    assert_eq!(height * 8, full_height);
+}
+
+#[test]
+fn highlights_i_and_sprite_sources() {
+    let chip = run(&PROGRAM, 1);
+    assert_eq!(chip.recent_sprites().collect::<Vec<_>>(), [(0x208, 2)]);
+
+    let mut view = MemoryView::new(1);
+    view.jump(0x200);
+    let (width, _, pixels) = view.image(&chip, &PALETTE);
+    // the first column starts below its address label
+    let pixel = |x: usize, address: usize| pixels[(7 + address - 0x200) * width + x];
+
+    assert_eq!(pixel(0, 0x208), PALETTE.foreground);
+    assert_eq!(pixel(5, 0x208), PALETTE.blend);
+    assert_eq!(pixel(3, 0x209), PALETTE.foreground);
+    assert_eq!(pixel(1, 0x209), PALETTE.blend);
+    assert_eq!(pixel(0, 0x20C), PALETTE.plane2);
+    assert_eq!(pixel(0, 0x20E), PALETTE.background);
+    // A208
+    assert_eq!(pixel(0, 0x200), PALETTE.foreground);
+    assert_eq!(pixel(1, 0x200), PALETTE.background);
+}
+
+#[test]
+fn recent_sprites_newest_first() {
+    let chip = run(&std::fs::read("roms/IBM Logo.ch8").unwrap(), 10);
+    let sprites = chip.recent_sprites().collect::<Vec<_>>();
+
+    assert_eq!(sprites.len(), 6);
+    assert!(sprites.len() <= RECENT_SPRITES);
+    assert_eq!(sprites[0].0, chip.cpu_state().i);
+    assert!(sprites.iter().all(|&(_, n)| n == 15));
+}
+
+#[test]
+fn png_has_the_view_size() {
+    let chip = run(&PROGRAM, 1);
+    let mut view = MemoryView::new(3);
+    view.jump(0x200);
+
+    let mut png = Vec::new();
+    view.write_png(&chip, &PALETTE, &mut png).unwrap();
+
+    let reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let info = reader.info();
+    let (width, height) = view.size();
+    assert_eq!((info.width as usize, info.height as usize), (width, height));
+}
@@ -0,0 +1,64 @@
+use chip_8::{engines::HeadlessEngine, Chip8, Engine, FrameState, Movie, Scheduler, Seed};
+
+const ROM: &str = "roms/Brix [Andreas Gustafsson, 1990].ch8";
+
+/// Plays Brix moving the paddle around, the way an engine records a session
+fn record(rom: &[u8], seed: u64) -> Movie {
+    let scheduler = Scheduler::default();
+    let mut chip = Chip8::new();
+    chip.set_rng(Seed::Fixed(seed).into_rng());
+    chip.load_game(rom);
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+
+    let mut movie = Movie::new(rom, seed, &chip.quircks, &scheduler);
+    for frame in 0..600 {
+        // key 4 moves left and key 6 moves right
+        let keypad = match frame / 100 % 3 {
+            0 => 1 << 4,
+            1 => 1 << 6,
+            _ => 0,
+        };
+        chip.set_keypad(keypad);
+        movie.record_frame(&chip);
+        scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    }
+    movie.finish(engine.framebuffer());
+
+    movie
+}
+
+#[test]
+fn replay_matches_recording() {
+    let rom = std::fs::read(ROM).unwrap();
+    let movie = record(&rom, 7);
+
+    let mut file = Vec::new();
+    movie.write(&mut file).unwrap();
+    let read = Movie::read(file.as_slice()).unwrap();
+    assert_eq!(read, movie);
+
+    read.play(&rom).unwrap();
+}
+
+/// The seed drawn for `--seed entropy` is recorded like a fixed one
+#[test]
+fn replay_entropy_seed() {
+    let rom = std::fs::read(ROM).unwrap();
+    let movie = record(&rom, Seed::Entropy.value());
+    movie.play(&rom).unwrap();
+}
+
+#[test]
+fn replay_detects_divergence() {
+    let rom = std::fs::read(ROM).unwrap();
+    let mut movie = record(&rom, 7);
+
+    for keypad in &mut movie.frames[100..300] {
+        *keypad = 0;
+    }
+    assert!(movie.play(&rom).is_err());
+
+    let other_rom = std::fs::read("roms/IBM Logo.ch8").unwrap();
+    assert!(record(&rom, 7).play(&other_rom).is_err());
+}
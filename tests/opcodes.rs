@@ -0,0 +1,368 @@
+//! Differential tests of every instruction: random machine states run one
+//! `emulate_cycle` and are compared with `reference_step`, a direct reading
+//! of the instruction set that decodes the opcode by itself.
+
+use chip_8::{engines::HeadlessEngine, Chip8, CpuState, Engine, Quircks, Rng, XorShiftRng};
+use proptest::{array, collection, prelude::*};
+
+/// Opcode patterns with the bits filled in by the generator
+const PATTERNS: [(u16, u16); 35] = [
+    (0x0000, 0x0FFF),
+    (0x00E0, 0x0000),
+    (0x00EE, 0x0000),
+    (0x1000, 0x0FFF),
+    (0x2000, 0x0FFF),
+    (0x3000, 0x0FFF),
+    (0x4000, 0x0FFF),
+    (0x5000, 0x0FF0),
+    (0x6000, 0x0FFF),
+    (0x7000, 0x0FFF),
+    (0x8000, 0x0FF0),
+    (0x8001, 0x0FF0),
+    (0x8002, 0x0FF0),
+    (0x8003, 0x0FF0),
+    (0x8004, 0x0FF0),
+    (0x8005, 0x0FF0),
+    (0x8006, 0x0FF0),
+    (0x8007, 0x0FF0),
+    (0x800E, 0x0FF0),
+    (0x9000, 0x0FF0),
+    (0xA000, 0x0FFF),
+    (0xB000, 0x0FFF),
+    (0xC000, 0x0FFF),
+    (0xD000, 0x0FFF),
+    (0xE09E, 0x0F00),
+    (0xE0A1, 0x0F00),
+    (0xF007, 0x0F00),
+    (0xF00A, 0x0F00),
+    (0xF015, 0x0F00),
+    (0xF018, 0x0F00),
+    (0xF01E, 0x0F00),
+    (0xF029, 0x0F00),
+    (0xF033, 0x0F00),
+    (0xF055, 0x0F00),
+    (0xF065, 0x0F00),
+];
+
+/// Everything an instruction can read or change
+#[derive(Debug, Clone)]
+struct Machine {
+    v: [u8; 16],
+    i: u16,
+    pc: u16,
+    sp: u8,
+    stack: [u16; 16],
+    delay_timer: u8,
+    sound_timer: u8,
+    memory: Vec<u8>,
+    /// Rows of pixels, bit 63 is the leftmost
+    screen: [u64; 32],
+    keypad: u16,
+}
+
+/// Registers, stack, timers and keypad
+type Registers = ([u8; 16], u16, u16, u8, [u16; 16], u8, u8, u16);
+
+impl Machine {
+    fn registers(&self) -> Registers {
+        (
+            self.v,
+            self.i,
+            self.pc,
+            self.sp,
+            self.stack,
+            self.delay_timer,
+            self.sound_timer,
+            self.keypad,
+        )
+    }
+}
+
+fn opcode() -> impl Strategy<Value = u16> {
+    (0..PATTERNS.len(), any::<u16>()).prop_map(|(n, bits)| {
+        let (base, mask) = PATTERNS[n];
+        base | (bits & mask)
+    })
+}
+
+prop_compose! {
+    /// Any state, addresses past the end of memory wrap around and
+    /// the stack after 16 entries
+    fn machine()(
+        v in any::<[u8; 16]>(),
+        i in any::<u16>(),
+        pc in 0..0x1000_u16,
+        sp in 0..16_u8,
+        stack in array::uniform16(any::<u16>()),
+        delay_timer in any::<u8>(),
+        sound_timer in any::<u8>(),
+        memory in collection::vec(any::<u8>(), 0x1000),
+        screen in array::uniform32(any::<u64>()),
+        keypad in prop_oneof![Just(0), any::<u16>()],
+    ) -> Machine {
+        Machine { v, i, pc, sp, stack, delay_timer, sound_timer, memory, screen, keypad }
+    }
+}
+
+prop_compose! {
+    fn quircks()(
+        load_store in any::<bool>(),
+        shift in any::<bool>(),
+        jump in any::<bool>(),
+        vf_reset in any::<bool>(),
+    ) -> Quircks {
+        Quircks { load_store, shift, jump, vf_reset }
+    }
+}
+
+/// Memory address `offset` bytes after `address`
+fn wrap(address: u16, offset: usize) -> usize {
+    (address as usize + offset) & 0xFFF
+}
+
+/// What the instruction set says `opcode` does
+fn reference_step(m: &mut Machine, opcode: u16, quircks: Quircks, rng: &mut XorShiftRng) {
+    let x = (opcode >> 8 & 0xF) as usize;
+    let y = (opcode >> 4 & 0xF) as usize;
+    let n = (opcode & 0xF) as usize;
+    let nn = opcode as u8;
+    let nnn = opcode & 0xFFF;
+    let next = wrap(m.pc, 2) as u16;
+    let skip = |condition: bool| {
+        if condition {
+            wrap(next, 2) as u16
+        } else {
+            next
+        }
+    };
+    let key = |k: u8| m.keypad & (1 << (k & 0xF)) != 0;
+
+    m.pc = match opcode >> 12 {
+        0x0 if opcode == 0x00E0 => {
+            m.screen = [0; 32];
+            next
+        }
+        0x0 if opcode == 0x00EE => {
+            m.sp = m.sp.wrapping_sub(1) & 0xF;
+            m.stack[m.sp as usize] & 0xFFF
+        }
+        // machine code routine, skipped
+        0x0 => next,
+        0x1 => nnn,
+        0x2 => {
+            m.stack[m.sp as usize] = next;
+            m.sp = (m.sp + 1) & 0xF;
+            nnn
+        }
+        0x3 => skip(m.v[x] == nn),
+        0x4 => skip(m.v[x] != nn),
+        0x5 => skip(m.v[x] == m.v[y]),
+        0x6 => {
+            m.v[x] = nn;
+            next
+        }
+        0x7 => {
+            m.v[x] = m.v[x].wrapping_add(nn);
+            next
+        }
+        0x8 => {
+            let (vx, vy) = (m.v[x], m.v[y]);
+            // the flag is written last, so it wins when X is F
+            let (result, flag) = match n {
+                0x0 => (vy, None),
+                0x1 => (vx | vy, quircks.vf_reset.then_some(0)),
+                0x2 => (vx & vy, quircks.vf_reset.then_some(0)),
+                0x3 => (vx ^ vy, quircks.vf_reset.then_some(0)),
+                0x4 => {
+                    let (sum, carry) = vx.overflowing_add(vy);
+                    (sum, Some(carry as u8))
+                }
+                0x5 => {
+                    let (difference, borrow) = vx.overflowing_sub(vy);
+                    (difference, Some(!borrow as u8))
+                }
+                0x6 => {
+                    let source = if quircks.shift { vx } else { vy };
+                    (source >> 1, Some(source & 1))
+                }
+                0x7 => {
+                    let (difference, borrow) = vy.overflowing_sub(vx);
+                    (difference, Some(!borrow as u8))
+                }
+                _ => {
+                    let source = if quircks.shift { vx } else { vy };
+                    (source << 1, Some(source >> 7))
+                }
+            };
+            m.v[x] = result;
+            if let Some(flag) = flag {
+                m.v[0xF] = flag;
+            }
+            next
+        }
+        0x9 => skip(m.v[x] != m.v[y]),
+        0xA => {
+            m.i = nnn;
+            next
+        }
+        0xB => {
+            let offset = if quircks.jump { m.v[x] } else { m.v[0] };
+            wrap(nnn, offset as usize) as u16
+        }
+        0xC => {
+            m.v[x] = rng.next_u8() & nn;
+            next
+        }
+        0xD => {
+            let (left, top) = (m.v[x] as usize, m.v[y] as usize);
+            let mut collision = false;
+            for row in 0..n {
+                let sprite = m.memory[wrap(m.i, row)];
+                for column in 0..8 {
+                    if sprite & (0x80 >> column) != 0 {
+                        let screen_row = &mut m.screen[(top + row) % 32];
+                        let bit = 1 << (63 - (left + column) % 64);
+                        collision |= *screen_row & bit != 0;
+                        *screen_row ^= bit;
+                    }
+                }
+            }
+            m.v[0xF] = collision as u8;
+            next
+        }
+        0xE if nn == 0x9E => skip(key(m.v[x])),
+        0xE => skip(!key(m.v[x])),
+        _ => {
+            let i = m.i;
+            match nn {
+                0x07 => m.v[x] = m.delay_timer,
+                0x0A => match (0..16).find(|&k| key(k)) {
+                    Some(k) => m.v[x] = k,
+                    // waits by running the instruction again
+                    None => return,
+                },
+                0x15 => m.delay_timer = m.v[x],
+                0x18 => m.sound_timer = m.v[x],
+                0x1E => m.i = wrap(m.i, m.v[x] as usize) as u16,
+                0x29 => m.i = (m.v[x] & 0xF) as u16 * 5,
+                0x33 => {
+                    m.memory[wrap(i, 0)] = m.v[x] / 100;
+                    m.memory[wrap(i, 1)] = m.v[x] / 10 % 10;
+                    m.memory[wrap(i, 2)] = m.v[x] % 10;
+                }
+                0x55 => {
+                    for r in 0..=x {
+                        m.memory[wrap(i, r)] = m.v[r];
+                    }
+                    if quircks.load_store {
+                        m.i = wrap(i, x + 1) as u16;
+                    }
+                }
+                _ => {
+                    for r in 0..=x {
+                        m.v[r] = m.memory[wrap(i, r)];
+                    }
+                    if quircks.load_store {
+                        m.i = wrap(i, x + 1) as u16;
+                    }
+                }
+            }
+            next
+        }
+    };
+}
+
+/// Runs `opcode` at `m.pc` on the emulator
+fn emulate(m: &Machine, opcode: u16, quircks: Quircks, seed: u64) -> (Machine, CpuState) {
+    let mut chip = Chip8::new();
+    chip.quircks = quircks;
+    chip.write_memory(0, &m.memory);
+    chip.write_memory(m.pc, &opcode.to_be_bytes());
+    chip.set_cpu_state(&CpuState {
+        v: m.v,
+        i: m.i,
+        pc: m.pc,
+        sp: m.sp,
+        stack: m.stack,
+        delay_timer: m.delay_timer,
+        sound_timer: m.sound_timer,
+        rng_state: XorShiftRng::new(seed).state(),
+    });
+    chip.set_keypad(m.keypad);
+
+    let mut engine = HeadlessEngine::new(0);
+    for (y, row) in m.screen.iter().enumerate() {
+        for (column, byte) in row.to_be_bytes().into_iter().enumerate() {
+            engine.draw_sprite(column as u8 * 8, y as u8, 1, &[byte]);
+        }
+    }
+
+    chip.emulate_cycle(&mut engine);
+
+    let state = chip.cpu_state();
+    let framebuffer = engine.framebuffer();
+    let mut screen = [0; 32];
+    for (y, row) in screen.iter_mut().enumerate() {
+        for x in 0..64 {
+            *row |= (framebuffer.pixel(x, y) as u64) << (63 - x);
+        }
+    }
+    let machine = Machine {
+        v: state.v,
+        i: state.i,
+        pc: state.pc,
+        sp: state.sp,
+        stack: state.stack,
+        delay_timer: state.delay_timer,
+        sound_timer: state.sound_timer,
+        memory: chip.memory().to_vec(),
+        screen,
+        keypad: chip.keypad(),
+    };
+
+    (machine, state)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn matches_reference(
+        machine in machine(),
+        opcode in opcode(),
+        quircks in quircks(),
+        seed in any::<u64>(),
+    ) {
+        let mut expected = machine.clone();
+        // the opcode itself is in memory, where FX33 or FX55 may overwrite it
+        let [hi, lo] = opcode.to_be_bytes();
+        expected.memory[wrap(machine.pc, 0)] = hi;
+        expected.memory[wrap(machine.pc, 1)] = lo;
+        let mut rng = XorShiftRng::new(seed);
+        reference_step(&mut expected, opcode, quircks, &mut rng);
+
+        let (actual, state) = emulate(&machine, opcode, quircks, seed);
+
+        prop_assert_eq!(actual.registers(), expected.registers(), "{:04X}", opcode);
+        let written = (0..4096).find(|&a| actual.memory[a] != expected.memory[a]);
+        prop_assert_eq!(written, None, "{:04X} memory differs", opcode);
+        prop_assert_eq!(actual.screen, expected.screen, "{:04X}", opcode);
+        prop_assert_eq!(state.rng_state, rng.state(), "{:04X}", opcode);
+    }
+
+    /// X or Y being F reads the operand before the flag overwrites it
+    #[test]
+    fn flag_register_as_operand(
+        machine in machine(),
+        n in prop::sample::select(vec![0x1_u16, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE]),
+        xy in prop::sample::select(vec![0xF0_u16, 0x0F, 0xFF]),
+        quircks in quircks(),
+    ) {
+        let opcode = 0x8000 | xy << 4 | n;
+        let mut expected = machine.clone();
+        reference_step(&mut expected, opcode, quircks, &mut XorShiftRng::default());
+
+        let (actual, _) = emulate(&machine, opcode, quircks, 0);
+        prop_assert_eq!(actual.v, expected.v, "{:04X}", opcode);
+    }
+}
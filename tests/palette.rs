@@ -0,0 +1,41 @@
+use chip_8::{Palette, PALETTES};
+
+#[test]
+fn named_palettes_parse() {
+    for (name, palette) in PALETTES {
+        assert_eq!(name.parse::<Palette>(), Ok(palette));
+    }
+    assert_eq!("classic".parse(), Ok(Palette::default()));
+}
+
+#[test]
+fn custom_colors_parse() {
+    let palette: Palette = "#000000,#FF00FF".parse().unwrap();
+    assert_eq!(palette.background, 0x00_00_00);
+    assert_eq!(palette.foreground, 0xFF_00_FF);
+    // unused planes get the average
+    assert_eq!(palette.plane2, 0x7F_00_7F);
+
+    let palette: Palette = "996600, ffcc00, ff6600, 662200".parse().unwrap();
+    assert_eq!(palette, Palette::OCTO);
+
+    // round trips through Display
+    assert_eq!(palette.to_string().parse(), Ok(palette));
+}
+
+#[test]
+fn invalid_palettes_are_rejected() {
+    assert!("purple".parse::<Palette>().is_err());
+    assert!("#000000".parse::<Palette>().is_err());
+    assert!("#000,#fff".parse::<Palette>().is_err());
+    assert!("0,1,2,3,4".parse::<Palette>().is_err());
+}
+
+#[test]
+fn colors_are_indexed_by_planes() {
+    let palette = Palette::OCTO;
+    assert_eq!(palette.color(0), palette.background);
+    assert_eq!(palette.color(1), palette.foreground);
+    assert_eq!(palette.color(2), palette.plane2);
+    assert_eq!(palette.color(3), palette.blend);
+}
@@ -0,0 +1,59 @@
+use chip_8::{
+    engines::{DebugPanel, HeadlessEngine, PANEL_WIDTH},
+    Chip8, Palette, CHAR_WIDTH,
+};
+
+const PALETTE: Palette = Palette {
+    background: 0x00_00_00,
+    foreground: 0xFF_FF_FF,
+    plane2: 0xFF_00_00,
+    blend: 0x00_00_FF,
+};
+
+/// 200: V3 = 2A, 202: call 206, 204: loop, 206: I = 123, 208: loop
+fn called() -> Chip8 {
+    let mut chip = Chip8::new();
+    chip.load_game(&[0x63, 0x2A, 0x22, 0x06, 0x12, 0x04, 0xA1, 0x23, 0x12, 0x08]);
+    let mut engine = HeadlessEngine::new(0);
+    for _ in 0..3 {
+        chip.emulate_cycle(&mut engine);
+    }
+
+    chip
+}
+
+#[test]
+fn lines_show_the_cpu_state() {
+    let lines = DebugPanel::new().lines(&called());
+
+    assert_eq!(lines[0], "PC 0208  I 0123");
+    assert_eq!(lines[1], "DT 00  ST 00  SP 1");
+    assert_eq!(lines[3], "V0 00 V1 00 V2 00 V3 2A");
+    assert_eq!(lines[6], "VC 00 VD 00 VE 00 VF 00");
+    assert!(lines[8].starts_with("> "), "{}", lines[8]);
+    assert_eq!(lines[10..12], ["STACK", "0 0204"]);
+    assert_eq!(lines.last().unwrap(), "FPS 0  IPS 0");
+}
+
+#[test]
+fn draws_over_the_right_columns() {
+    let (width, height) = (PANEL_WIDTH + 100, 200);
+    let mut buffer = vec![0x12_34_56; width * height];
+    DebugPanel::new().draw(&called(), &mut buffer, width, height, &PALETTE);
+
+    let left = width - PANEL_WIDTH;
+    for row in buffer.chunks(width) {
+        assert!(row[..left].iter().all(|&p| p == 0x12_34_56));
+        // two pixel wide separator
+        assert_eq!(row[left..left + 2], [PALETTE.plane2; 2]);
+    }
+
+    // "P" of the first line, scaled by 2 after the 8 pixel margin
+    let pixel = |x: usize, y: usize| buffer[(8 + y) * width + left + 8 + x];
+    assert_eq!(pixel(0, 0), PALETTE.foreground);
+    assert_eq!(pixel(3, 1), PALETTE.foreground);
+    assert_eq!(pixel(4, 0), PALETTE.background);
+    // the second line starts 7 font pixels lower
+    assert_eq!(pixel(0, 14), PALETTE.foreground);
+    assert_eq!(pixel(CHAR_WIDTH * 2 * 2, 6), PALETTE.background);
+}
@@ -0,0 +1,89 @@
+use chip_8::{engines::HeadlessEngine, Chip8, Recompiler};
+
+/// 200: V0 = 0, 202: skip if V0 == 1, 204: goto 208, 206: V1 = 5 (skipped),
+/// 208: call 20E, 20A: call 20E, 20C: loop, 20E: return, 210: data
+const PROGRAM: [u8; 18] = [
+    0x60, 0x00, 0x30, 0x01, 0x12, 0x08, 0x61, 0x05, 0x22, 0x0E, 0x22, 0x0E, 0x12, 0x0C, 0x00, 0xEE,
+    0xF0, 0x90,
+];
+
+fn profile(cycles: usize) -> Chip8 {
+    let mut chip = Chip8::new();
+    chip.load_game(&PROGRAM);
+    chip.set_profiling(true);
+    let mut engine = HeadlessEngine::new(0);
+    for _ in 0..cycles {
+        chip.emulate_cycle(&mut engine);
+    }
+
+    chip
+}
+
+#[test]
+fn disabled_by_default() {
+    assert!(Chip8::new().profile().is_none());
+}
+
+#[test]
+fn counts_instructions_and_calls() {
+    let chip = profile(20);
+    let profile = chip.profile().unwrap();
+
+    assert_eq!(profile.total(), 20);
+    assert_eq!(profile.executed(0x200), 1);
+    assert_eq!(profile.executed(0x206), 0);
+    assert_eq!(profile.executed(0x20E), 2);
+    // 20 - 7 instructions before the loop
+    assert_eq!(profile.executed(0x20C), 13);
+    assert_eq!(profile.hot_addresses()[0], (0x20C, 13));
+
+    assert_eq!(profile.calls(0x20E), 2);
+    assert_eq!(profile.subroutines(), [(0x20E, 2)]);
+
+    let opcodes = profile.opcodes();
+    assert_eq!(opcodes[0], ("1NNN", 14));
+    assert!(opcodes.contains(&("2NNN", 2)));
+    assert!(opcodes.contains(&("00EE", 2)));
+}
+
+#[test]
+fn report_finds_never_executed_code() {
+    let chip = profile(20);
+    let report = chip.profile().unwrap().report(chip.memory(), PROGRAM.len());
+
+    assert_eq!(report.never_executed(), [0x206..=0x206]);
+    assert_eq!(report.coverage(), 7.0 / 8.0);
+
+    let text = report.to_string();
+    assert!(text.starts_with("20 instructions, 87.5% of the ROM's code executed"));
+    assert!(text.contains("Never executed"));
+}
+
+#[test]
+fn annotated_disassembly() {
+    let chip = profile(20);
+    let report = chip.profile().unwrap().report(chip.memory(), PROGRAM.len());
+    let disassembly = report.annotated_disassembly();
+    let lines: Vec<_> = disassembly.lines().map(str::trim).collect();
+
+    assert_eq!(lines[0], "1  200  6000  V0=00");
+    assert!(lines.contains(&"-  206  6105  V1=05"));
+    assert!(lines.contains(&"sub_20E: called 2 times"));
+    assert_eq!(lines.last(), Some(&"210-211  data, 2 bytes"));
+}
+
+#[test]
+fn recompiler_is_profiled() {
+    let mut chip = Chip8::new();
+    chip.load_game(&PROGRAM);
+    chip.set_profiling(true);
+    let mut engine = HeadlessEngine::new(0);
+    Recompiler::new().run(&mut chip, &mut engine, 20);
+
+    let recompiled = chip.profile().unwrap();
+    let interpreted = profile(20);
+    let interpreted = interpreted.profile().unwrap();
+    assert_eq!(recompiled.hot_addresses(), interpreted.hot_addresses());
+    assert_eq!(recompiled.subroutines(), interpreted.subroutines());
+    assert_eq!(recompiled.opcodes(), interpreted.opcodes());
+}
@@ -0,0 +1,118 @@
+use std::path::Path;
+
+use chip_8::{engines::HeadlessEngine, Chip8, Quircks, RomMetadata};
+
+/// V0 = 0x81, V1 = 0x02, VF = 0x01, then `opcode`
+fn run(opcode: u16, quircks: Quircks) -> Chip8 {
+    let [hi, lo] = opcode.to_be_bytes();
+    let mut chip = Chip8::new();
+    chip.quircks = quircks;
+    chip.load_game(&[0x60, 0x81, 0x61, 0x02, 0x6F, 0x01, hi, lo]);
+    let mut engine = HeadlessEngine::new(0);
+    for _ in 0..4 {
+        chip.emulate_cycle(&mut engine);
+    }
+
+    chip
+}
+
+fn shift(opcode: u16, quircks: Quircks) -> [u8; 16] {
+    run(opcode, quircks).cpu_state().v
+}
+
+#[test]
+fn shifts_vy_by_default() {
+    let v = shift(0x8016, Quircks::default());
+    assert_eq!((v[0], v[0xF]), (0x01, 0));
+
+    let v = shift(0x801E, Quircks::default());
+    assert_eq!((v[0], v[0xF]), (0x04, 0));
+}
+
+#[test]
+fn shift_quirk_shifts_vx_in_place() {
+    let quircks = Quircks {
+        shift: true,
+        ..Default::default()
+    };
+
+    let v = shift(0x8016, quircks);
+    assert_eq!((v[0], v[0xF]), (0x40, 1));
+
+    let v = shift(0x801E, quircks);
+    assert_eq!((v[0], v[0xF]), (0x02, 1));
+}
+
+#[test]
+fn jump_quirk_adds_vx() {
+    assert_eq!(run(0xB123, Quircks::default()).pc(), 0x1A4);
+
+    let quircks = Quircks {
+        jump: true,
+        ..Default::default()
+    };
+    assert_eq!(run(0xB123, quircks).pc(), 0x125);
+}
+
+#[test]
+fn vf_reset_quirk_clears_the_flag() {
+    let quircks = Quircks {
+        vf_reset: true,
+        ..Default::default()
+    };
+    for opcode in [0x8011, 0x8012, 0x8013] {
+        assert_eq!(shift(opcode, Quircks::default())[0xF], 1);
+        assert_eq!(shift(opcode, quircks)[0xF], 0);
+    }
+}
+
+#[test]
+fn roms_json_quirks_are_applied() {
+    let metadata = RomMetadata::find(Path::new("roms/Blinky [Hans Christian Egeberg, 1991].ch8"));
+    let mut quircks = Quircks::default();
+    metadata.unwrap().apply_quircks(&mut quircks);
+
+    assert_eq!(
+        quircks,
+        Quircks {
+            load_store: false,
+            shift: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn roms_json_defaults_to_incrementing_i() {
+    let metadata = RomMetadata::find(Path::new("roms/Space Invaders [David Winter].ch8"));
+    let mut quircks = Quircks::default();
+    metadata.unwrap().apply_quircks(&mut quircks);
+
+    assert_eq!(
+        quircks,
+        Quircks {
+            load_store: true,
+            shift: true,
+            ..Default::default()
+        }
+    );
+}
+
+#[test]
+fn names_round_trip() {
+    let quircks = Quircks {
+        load_store: true,
+        shift: true,
+        jump: true,
+        vf_reset: true,
+    };
+    assert_eq!(quircks.to_string(), "load_store,shift,jump,vf_reset");
+    assert_eq!("load_store,shift,jump,vf_reset".parse(), Ok(quircks));
+
+    assert_eq!(Quircks::default().to_string(), "none");
+    assert_eq!("none".parse(), Ok(Quircks::default()));
+    assert!("wrap"
+        .parse::<Quircks>()
+        .unwrap_err()
+        .contains("unknown quirk wrap"));
+}
@@ -0,0 +1,153 @@
+use chip_8::{engines::HeadlessEngine, Chip8, CpuState, Recompiler};
+
+/// Runs the same program through `emulate_cycle` and the recompiler,
+/// comparing the machines after every batch of instructions
+fn lock_step(program: &[u8], frames: usize) -> Chip8 {
+    let mut reference = Chip8::new();
+    reference.load_game(program);
+    let mut reference_engine = HeadlessEngine::new(0);
+
+    let mut recompiled = Chip8::new();
+    recompiled.load_game(program);
+    let mut recompiled_engine = HeadlessEngine::new(0);
+    let mut recompiler = Recompiler::new();
+
+    for frame in 0..frames {
+        // uneven batch sizes so blocks get cut at different points
+        let mut remaining = 10;
+        for cycles in [1, 4, 2, 3] {
+            for _ in 0..cycles {
+                reference.emulate_cycle(&mut reference_engine);
+            }
+            recompiler.run(&mut recompiled, &mut recompiled_engine, cycles);
+            remaining -= cycles;
+
+            assert_eq!(
+                reference.cpu_state(),
+                recompiled.cpu_state(),
+                "frame {}",
+                frame
+            );
+        }
+        assert_eq!(remaining, 0);
+        assert_eq!(reference.cycles(), recompiled.cycles(), "frame {}", frame);
+        assert_eq!(reference.memory(), recompiled.memory(), "frame {}", frame);
+
+        reference.decrement_timers();
+        recompiled.decrement_timers();
+    }
+
+    recompiled
+}
+
+#[test]
+fn roms_match_interpreter() {
+    for path in [
+        "roms/IBM Logo.ch8",
+        "roms/Particle Demo [zeroZshadow, 2008].ch8",
+        "roms/Sierpinski [Sergey Naydenov, 2010].ch8",
+        "roms/Trip8 Demo (2008) [Revival Studios].ch8",
+        "roms/Tetris [Fran Dachille, 1991].ch8",
+        "roms/Brix [Andreas Gustafsson, 1990].ch8",
+        "roms/Division Test [Sergey Naydenov, 2010].ch8",
+    ] {
+        let data = std::fs::read(path).unwrap();
+        lock_step(&data, 2_000);
+    }
+}
+
+#[test]
+fn self_modifying_code() {
+    #[rustfmt::skip]
+    let program = [
+        0x60, 0x6A, // 200: V0 = 6A
+        0x71, 0x01, // 202: V1 += 1
+        0xA2, 0x08, // 204: I = 208
+        0xF1, 0x55, // 206: store V0..V1 at I, rewrites the next instruction
+        0x6A, 0x00, // 208: VA = V1
+        0x12, 0x02, // 20A: goto 202
+    ];
+
+    let chip = lock_step(&program, 50);
+    let state = chip.cpu_state();
+    assert_ne!(state.v[1], 0);
+    assert_eq!(state.v[0xA], state.v[1]);
+}
+
+#[test]
+fn instructions_wrap_around_memory() {
+    #[rustfmt::skip]
+    let program = [
+        0x60, 0x12, // 200: V0 = 12
+        0x61, 0x10, // 202: V1 = 10
+        0xA0, 0x01, // 204: I = 001
+        0xF1, 0x55, // 206: store "goto 210" at 001
+        0x60, 0x60, // 208: V0 = 60
+        0x61, 0x07, // 20A: V1 = 07
+        0xAF, 0xFF, // 20C: I = FFF
+        0xF1, 0x55, // 20E: store "V0 = 07" at FFF, its second byte at 000
+        0x73, 0x01, // 210: V3 += 1
+        0x1F, 0xFF, // 212: goto FFF
+    ];
+
+    let chip = lock_step(&program, 20);
+    let state = chip.cpu_state();
+    assert_eq!(state.v[0], 0x07);
+    assert_ne!(state.v[3], 0);
+}
+
+#[test]
+fn memory_written_between_runs() {
+    #[rustfmt::skip]
+    let program = [
+        0x60, 0x11, // 200: V0 = 11
+        0x12, 0x00, // 202: goto 200
+        0x00, 0x00,
+        0xF1, 0x55, // 206: store V0..V1 at I
+    ];
+
+    let mut reference = Chip8::new();
+    let mut recompiled = Chip8::new();
+    let mut engine = HeadlessEngine::new(0);
+    let mut recompiler = Recompiler::new();
+
+    // stops at 200 with its block cached, before every change
+    let mut run = |reference: &mut Chip8, recompiled: &mut Chip8| {
+        for _ in 0..2 {
+            reference.emulate_cycle(&mut engine);
+        }
+        recompiler.run(recompiled, &mut engine, 2);
+        assert_eq!(reference.cpu_state(), recompiled.cpu_state());
+        recompiled.cpu_state().v[0]
+    };
+
+    for chip in [&mut reference, &mut recompiled] {
+        chip.load_game(&program);
+    }
+    // twice, so the second run caches the block with no writes pending
+    assert_eq!(run(&mut reference, &mut recompiled), 0x11);
+    assert_eq!(run(&mut reference, &mut recompiled), 0x11);
+
+    for chip in [&mut reference, &mut recompiled] {
+        chip.write_memory(0x200, &[0x60, 0x22]);
+    }
+    assert_eq!(run(&mut reference, &mut recompiled), 0x22);
+
+    for chip in [&mut reference, &mut recompiled] {
+        chip.load_game(&[0x60, 0x33]);
+    }
+    assert_eq!(run(&mut reference, &mut recompiled), 0x33);
+
+    // the interpreter runs 206 and stores 60 44, "V0 = 44", at 200
+    for chip in [&mut reference, &mut recompiled] {
+        let mut state = chip.cpu_state();
+        (state.v[0], state.v[1], state.i, state.pc) = (0x60, 0x44, 0x200, 0x206);
+        chip.set_cpu_state(&state);
+        chip.emulate_cycle(&mut HeadlessEngine::new(0));
+        chip.set_cpu_state(&CpuState {
+            pc: 0x200,
+            ..chip.cpu_state()
+        });
+    }
+    assert_eq!(run(&mut reference, &mut recompiled), 0x44);
+}
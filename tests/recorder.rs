@@ -0,0 +1,73 @@
+use std::{fs::File, path::PathBuf};
+
+use chip_8::{engines::PixelBuf, Palette, Recorder};
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("chip8-recorder-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn gif_merges_identical_frames() {
+    let path = temp_path("merge.gif");
+    let mut recorder = Recorder::create(&path, 1, Palette::default()).unwrap();
+
+    let mut framebuffer = PixelBuf::new();
+    for _ in 0..30 {
+        recorder.capture(&framebuffer).unwrap();
+    }
+    framebuffer.draw_sprite(0, 0, 1, &[0b1000_0000]);
+    for _ in 0..90 {
+        recorder.capture(&framebuffer).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(File::open(&path).unwrap()).unwrap();
+    let mut delays = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().unwrap() {
+        assert_eq!((frame.width, frame.height), (64, 32));
+        delays.push(frame.delay);
+    }
+    std::fs::remove_file(&path).unwrap();
+
+    // half a second blank, then a second and a half with the pixel set
+    assert_eq!(delays, [50, 150]);
+}
+
+#[test]
+fn y4m_has_a_header_and_full_frames() {
+    let path = temp_path("frames.y4m");
+    let mut recorder = Recorder::create(&path, 2, Palette::default()).unwrap();
+    let framebuffer = PixelBuf::new();
+    for _ in 0..3 {
+        recorder.capture(&framebuffer).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    let data = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let header = b"YUV4MPEG2 W128 H64 F60:1 Ip A1:1 C444\n";
+    assert!(data.starts_with(header));
+    let frame = b"FRAME\n".len() + 128 * 64 * 3;
+    assert_eq!(data.len(), header.len() + 3 * frame);
+    // black is 16 in studio swing
+    assert_eq!(data[header.len() + b"FRAME\n".len()], 16);
+}
+
+#[test]
+fn png_sequence_writes_one_file_per_frame() {
+    let path = temp_path("frames");
+    let mut recorder = Recorder::create(&path, 1, Palette::default()).unwrap();
+    let framebuffer = PixelBuf::new();
+    for _ in 0..2 {
+        recorder.capture(&framebuffer).unwrap();
+    }
+    recorder.finish().unwrap();
+
+    assert!(path.join("frame-000000.png").exists());
+    assert!(path.join("frame-000001.png").exists());
+    assert!(!path.join("frame-000002.png").exists());
+    std::fs::remove_dir_all(&path).unwrap();
+}
@@ -0,0 +1,91 @@
+use chip_8::engines::{ScaleMode, Scaler};
+
+const ON: u32 = 0xFF_FF_FF;
+const OFF: u32 = 0x00_00_00;
+
+/// Renders a picture with only the top left pixel lit
+fn render(scaler: &Scaler, width: usize, height: usize) -> Vec<u32> {
+    let mut buffer = vec![0x12_34_56; width * height];
+    scaler.render(&mut buffer, width, height, OFF, |x, y| {
+        if (x, y) == (0, 0) {
+            ON
+        } else {
+            OFF
+        }
+    });
+    buffer
+}
+
+#[test]
+fn small_scales_drop_the_grid_instead_of_panicking() {
+    for scale in 1..5 {
+        let (width, height) = (64 * scale, 32 * scale);
+        let buffer = render(&Scaler::default(), width, height);
+
+        assert_eq!(buffer[0], ON);
+        assert_eq!(buffer[(scale - 1) * width + scale - 1], ON);
+        assert_eq!(buffer[scale], OFF);
+    }
+}
+
+#[test]
+fn grid_gap_is_split_around_cells() {
+    let scaler = Scaler {
+        gap: 2,
+        ..Default::default()
+    };
+    let width = 64 * 6;
+    let buffer = render(&scaler, width, 32 * 6);
+
+    // one background pixel before and after the lit 4x4 square
+    let row: Vec<_> = buffer[width..width + 6].to_vec();
+    assert_eq!(row, [OFF, ON, ON, ON, ON, OFF]);
+    assert_eq!(buffer[1], OFF);
+    assert_eq!(buffer[4 * width + 1], ON);
+    assert_eq!(buffer[5 * width + 1], OFF);
+}
+
+#[test]
+fn integer_scaling_centers_the_picture() {
+    let scaler = Scaler {
+        gap: 0,
+        ..Default::default()
+    };
+    // room for scale 2 with 10 pixels left over on each axis
+    assert_eq!(scaler.viewport(138, 74), (5, 5, 128, 64));
+
+    let buffer = render(&scaler, 138, 74);
+    assert_eq!(buffer[4 * 138 + 4], OFF);
+    assert_eq!(buffer[5 * 138 + 5], ON);
+    assert_eq!(buffer[6 * 138 + 6], ON);
+    assert_eq!(buffer[7 * 138 + 7], OFF);
+}
+
+#[test]
+fn aspect_scaling_fills_the_window() {
+    let scaler = Scaler {
+        mode: ScaleMode::Aspect,
+        ..Default::default()
+    };
+    assert_eq!(scaler.viewport(100, 100), (0, 25, 100, 50));
+    assert_eq!(scaler.viewport(300, 100), (50, 0, 200, 100));
+}
+
+#[test]
+fn scanlines_dim_odd_rows() {
+    let scaler = Scaler {
+        gap: 0,
+        scanlines: true,
+        ..Default::default()
+    };
+    let width = 64 * 4;
+    let buffer = render(&scaler, width, 32 * 4);
+
+    assert_eq!(buffer[0], ON);
+    assert_eq!(buffer[width], 0x7F_7F_7F);
+}
+
+#[test]
+fn windows_smaller_than_the_picture_are_clipped() {
+    render(&Scaler::default(), 10, 10);
+}
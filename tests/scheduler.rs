@@ -0,0 +1,227 @@
+use std::{
+    cell::Cell,
+    time::{Duration, Instant},
+};
+
+use chip_8::{engines::HeadlessEngine, Chip8, Clock, FramePacer, FrameState, Scheduler, Timing};
+
+#[test]
+fn instructions_per_frame() {
+    let mut scheduler = Scheduler::default();
+    assert_eq!(scheduler.instructions_per_frame(), 10);
+
+    scheduler.cpu_hz = 1000;
+    assert_eq!(scheduler.instructions_per_frame(), 16);
+
+    scheduler.ipf = Some(30);
+    assert_eq!(scheduler.instructions_per_frame(), 30);
+}
+
+/// Runs `frames` frames of an endless loop, returns the instructions run
+fn instructions_in(scheduler: &Scheduler, frames: usize) -> u64 {
+    let mut chip = Chip8::new();
+    // 200: goto 200
+    chip.load_game(&[0x12, 0x00]);
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+    for _ in 0..frames {
+        scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    }
+
+    chip.cycles()
+}
+
+#[test]
+fn cpu_hz_is_exact() {
+    let scheduler = Scheduler {
+        cpu_hz: 1000,
+        ..Default::default()
+    };
+    assert_eq!(instructions_in(&scheduler, 60), 1000);
+    assert_eq!(instructions_in(&scheduler, 3), 50);
+
+    let scheduler = Scheduler {
+        cpu_hz: 30,
+        ..Default::default()
+    };
+    assert_eq!(instructions_in(&scheduler, 60), 30);
+    assert_eq!(instructions_in(&scheduler, 1), 0);
+
+    let scheduler = Scheduler {
+        cpu_hz: 1000,
+        ipf: Some(7),
+        ..Default::default()
+    };
+    assert_eq!(instructions_in(&scheduler, 60), 420);
+}
+
+/// Time that only passes when the pacer sleeps or the test says so
+struct FakeClock {
+    start: Instant,
+    elapsed: Cell<Duration>,
+}
+
+impl FakeClock {
+    fn new() -> Self {
+        FakeClock {
+            start: Instant::now(),
+            elapsed: Cell::new(Duration::ZERO),
+        }
+    }
+
+    fn advance(&self, duration: Duration) {
+        self.elapsed.set(self.elapsed.get() + duration);
+    }
+
+    fn elapsed(&self) -> Duration {
+        self.elapsed.get()
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed.get()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+#[test]
+fn pacer_does_not_drift() {
+    let clock = FakeClock::new();
+    let period = Duration::from_millis(5);
+    let mut pacer = FramePacer::with_clock(period, &clock);
+
+    let mut frames = 0;
+    while frames < 40 {
+        frames += pacer.wait();
+        // pretend emulating and presenting take a variable amount of time
+        clock.advance(Duration::from_micros(500 * (frames as u64 % 3)));
+    }
+
+    assert_eq!(frames, 40);
+    assert_eq!(clock.elapsed(), period * 39 + Duration::from_micros(500));
+    assert_eq!(pacer.dropped_frames(), 0);
+}
+
+#[test]
+fn pacer_catches_up_then_drops() {
+    let clock = FakeClock::new();
+    let period = Duration::from_millis(20);
+    let mut pacer = FramePacer::with_clock(period, &clock);
+    assert_eq!(pacer.wait(), 1);
+
+    clock.advance(period * 2 + period / 2);
+    assert_eq!(pacer.wait(), 2);
+    assert_eq!(pacer.dropped_frames(), 0);
+
+    clock.advance(period * 10);
+    assert_eq!(pacer.wait(), pacer.max_catch_up);
+    assert_eq!(pacer.dropped_frames(), 6);
+
+    // back on a grid starting after the slow frame
+    assert_eq!(pacer.wait(), 1);
+    assert_eq!(pacer.dropped_frames(), 6);
+}
+
+#[test]
+fn vip_timing_waits_for_vblank_after_draw() {
+    let scheduler = Scheduler {
+        timing: Timing::Vip,
+        ..Default::default()
+    };
+    let mut chip = Chip8::new();
+    chip.load_game(&std::fs::read("roms/IBM Logo.ch8").unwrap());
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+
+    // 00E0, ANNN, 6XNN, 6XNN, DXYN
+    scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    assert_eq!(chip.pc(), 0x20A);
+
+    // 7XNN, ANNN, DXYN
+    scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    assert_eq!(chip.pc(), 0x210);
+}
+
+#[test]
+fn frames_count_cycles() {
+    let scheduler = Scheduler {
+        timing: Timing::Vip,
+        ..Default::default()
+    };
+    let mut chip = Chip8::new();
+    chip.load_game(&std::fs::read("roms/IBM Logo.ch8").unwrap());
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+
+    scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    assert_eq!(chip.cycles(), 8);
+}
+
+#[test]
+fn vip_timing_budget() {
+    let scheduler = Scheduler {
+        timing: Timing::Vip,
+        ..Default::default()
+    };
+    let mut chip = Chip8::new();
+    // 200: V0 += 1, 202: goto 200
+    chip.load_game(&[0x70, 0x01, 0x12, 0x00]);
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+
+    scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    // 7XNN is 50 cycles and 1NNN 52, so ~36 loops fit in 3668 cycles
+    assert_eq!(chip.cpu_state().v[0], 36);
+}
+
+#[test]
+fn vip_timing_carries_overrun() {
+    let scheduler = Scheduler {
+        timing: Timing::Vip,
+        ..Default::default()
+    };
+    let mut chip = Chip8::new();
+    // 200: V0 += 1, 202: goto 200
+    chip.load_game(&[0x70, 0x01, 0x12, 0x00]);
+    let mut engine = HeadlessEngine::new(0);
+    let mut state = FrameState::default();
+
+    for _ in 0..100 {
+        scheduler.run_frame(&mut state, &mut chip, &mut engine);
+    }
+    // 100 frames are 366800 cycles: 3596 loops of 102 and the 7XNN running past
+    // the end, instead of 72 instructions and 4 lost cycles every frame
+    assert_eq!(chip.cycles(), 3596 * 2 + 1);
+}
+
+#[test]
+fn vip_timing_counts_skips_around_the_end_of_memory() {
+    let scheduler = Scheduler {
+        timing: Timing::Vip,
+        ..Default::default()
+    };
+    // skip if V0 == 0 at `skip`, which always skips the next instruction,
+    // then goto `skip` from `jump`
+    let cycles = |skip: u16, jump: u16| {
+        let mut chip = Chip8::new();
+        chip.write_memory(skip, &[0x30, 0x00]);
+        chip.write_memory(jump, &[0x10 | (skip >> 8) as u8, skip as u8]);
+        let mut cpu = chip.cpu_state();
+        cpu.pc = skip;
+        chip.set_cpu_state(&cpu);
+
+        let mut engine = HeadlessEngine::new(0);
+        let mut state = FrameState::default();
+        for _ in 0..10 {
+            scheduler.run_frame(&mut state, &mut chip, &mut engine);
+        }
+        chip.cycles()
+    };
+
+    assert_eq!(cycles(0xFFC, 0x000), cycles(0x300, 0x304));
+}
@@ -0,0 +1,30 @@
+use chip_8::{engines::PixelBuf, write_png, Palette};
+
+#[test]
+fn png_is_scaled_and_colored() {
+    let mut framebuffer = PixelBuf::new();
+    // a single pixel in the top left corner
+    framebuffer.draw_sprite(0, 0, 1, &[0b1000_0000]);
+    let palette = Palette {
+        background: 0x10_20_30,
+        foreground: 0xAA_BB_CC,
+        ..Default::default()
+    };
+
+    let mut png = Vec::new();
+    write_png(&framebuffer, 3, &palette, &mut png).unwrap();
+
+    let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    assert_eq!((info.width, info.height), (64 * 3, 32 * 3));
+
+    let rgb = |x: usize, y: usize| {
+        let i = (y * info.width as usize + x) * 3;
+        [data[i], data[i + 1], data[i + 2]]
+    };
+    assert_eq!(rgb(0, 0), [0xAA, 0xBB, 0xCC]);
+    assert_eq!(rgb(2, 2), [0xAA, 0xBB, 0xCC]);
+    assert_eq!(rgb(3, 0), [0x10, 0x20, 0x30]);
+    assert_eq!(rgb(0, 3), [0x10, 0x20, 0x30]);
+}
@@ -0,0 +1,108 @@
+use chip_8::engines::{Cell, CellChange, Change, TermDiff};
+
+fn lines(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+#[test]
+fn first_frame_is_drawn_completely() {
+    let mut diff = TermDiff::new();
+    let changes = diff.changes(&lines(&["ab", "  "]));
+    assert_eq!(
+        changes,
+        [
+            Change {
+                row: 0,
+                column: 0,
+                text: "ab".to_owned()
+            },
+            Change {
+                row: 1,
+                column: 0,
+                text: "  ".to_owned()
+            },
+        ]
+    );
+}
+
+#[test]
+fn only_changed_cells_are_printed() {
+    let mut diff = TermDiff::new();
+    diff.changes(&lines(&["            ", "            "]));
+
+    assert!(diff
+        .changes(&lines(&["            ", "            "]))
+        .is_empty());
+
+    let changes = diff.changes(&lines(&["  █         ", "         ▀▀ "]));
+    assert_eq!(
+        changes,
+        [
+            Change {
+                row: 0,
+                column: 2,
+                text: "█".to_owned()
+            },
+            Change {
+                row: 1,
+                column: 9,
+                text: "▀▀".to_owned()
+            },
+        ]
+    );
+}
+
+#[test]
+fn close_changes_are_merged_into_one_run() {
+    let mut diff = TermDiff::new();
+    diff.changes(&lines(&["                "]));
+
+    let changes = diff.changes(&lines(&["#   #          #"]));
+    assert_eq!(
+        changes,
+        [
+            Change {
+                row: 0,
+                column: 0,
+                text: "#   #".to_owned()
+            },
+            Change {
+                row: 0,
+                column: 15,
+                text: "#".to_owned()
+            },
+        ]
+    );
+}
+
+#[test]
+fn invalidate_redraws_everything() {
+    let mut diff = TermDiff::new();
+    diff.changes(&lines(&["ab"]));
+    diff.invalidate();
+    assert_eq!(diff.changes(&lines(&["ab"])).len(), 1);
+}
+
+#[test]
+fn cell_changes_compare_colors() {
+    let cell = |ch, foreground| Cell {
+        ch,
+        foreground,
+        background: 0,
+    };
+    let mut diff = TermDiff::new();
+    diff.cell_changes(&[vec![cell('▀', 0xFFFFFF); 8]]);
+
+    // same character in another color
+    let mut row = vec![cell('▀', 0xFFFFFF); 8];
+    row[6] = cell('▀', 0xFF0000);
+    assert_eq!(
+        diff.cell_changes(&[row.clone()]),
+        [CellChange {
+            row: 0,
+            column: 6,
+            cells: vec![cell('▀', 0xFF0000)]
+        }]
+    );
+    assert!(diff.cell_changes(&[row]).is_empty());
+}
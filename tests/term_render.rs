@@ -0,0 +1,44 @@
+use chip_8::engines::{PixelBuf, TermRender};
+
+/// Pixels (0, 0), (1, 1) and (0, 3)
+fn framebuffer() -> PixelBuf {
+    let mut framebuffer = PixelBuf::new();
+    framebuffer.draw_sprite(0, 0, 4, &[0b1000_0000, 0b0100_0000, 0, 0b1000_0000]);
+    framebuffer
+}
+
+#[test]
+fn ascii_uses_a_character_per_pixel() {
+    let lines = TermRender::Ascii.lines(&framebuffer());
+    assert_eq!(lines.len(), 32);
+    assert_eq!(lines[0].chars().count(), 64);
+    assert!(lines[0].starts_with("# "));
+    assert!(lines[1].starts_with(" #"));
+}
+
+#[test]
+fn half_blocks_stack_two_rows() {
+    let lines = TermRender::HalfBlock.lines(&framebuffer());
+    assert_eq!(lines.len(), 16);
+    assert_eq!(lines[0].chars().count(), 64);
+    assert!(lines[0].starts_with("▀▄ "));
+    assert!(lines[1].starts_with("▄ "));
+}
+
+#[test]
+fn braille_packs_two_by_four_pixels() {
+    let lines = TermRender::Braille.lines(&framebuffer());
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[0].chars().count(), 32);
+    // dots 1, 5 and 7
+    assert_eq!(lines[0].chars().next(), Some('\u{2851}'));
+    assert_eq!(lines[0].chars().nth(1), Some('\u{2800}'));
+}
+
+#[test]
+fn renderers_parse() {
+    assert_eq!("ascii".parse(), Ok(TermRender::Ascii));
+    assert_eq!("half-block".parse(), Ok(TermRender::HalfBlock));
+    assert_eq!("braille".parse(), Ok(TermRender::Braille));
+    assert!("sixel".parse::<TermRender>().is_err());
+}